@@ -0,0 +1,154 @@
+//! Command-line tool for checking, converting and querying RSON.
+//!
+//! Reads the input from the given file, or stdin if there is none.
+
+extern crate rson_rs as rson;
+extern crate serde_json;
+
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+use rson::convert::{json_to_rson, rson_to_json};
+use rson::de::Error;
+use rson::document::{self, Document};
+use rson::value::Value;
+
+const USAGE: &str = "Usage: rson <COMMAND> [FILE]
+
+Commands:
+    check         Deserialize the input into a value and report errors
+    to-json       Convert RSON to JSON, see the `convert` module docs
+    from-json     Convert JSON to RSON
+    get <PATH>    Print the value at PATH, e.g. `level.buildings[0]`
+    pretty        Format the input
+    minify        Remove comments and whitespace";
+
+fn usage() -> !
+{
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+struct Input
+{
+    name: String,
+    text: String,
+}
+
+impl Input
+{
+    fn read(file: Option<String>) -> Self
+    {
+        let res = match file {
+            Some(ref file) => fs::read_to_string(file),
+            None => {
+                let mut text = String::new();
+
+                io::stdin().read_to_string(&mut text).map(|_| text)
+            }
+        };
+
+        let name = file.unwrap_or_else(|| "<stdin>".to_owned());
+
+        match res {
+            Ok(text) => Input { name, text },
+            Err(e) => {
+                eprintln!("error: {}: {}", name, e);
+                process::exit(1);
+            }
+        }
+    }
+
+    /// Reports `e`, with the offending line if its position is known.
+    fn fail(&self, e: &Error) -> !
+    {
+        match *e {
+            Error::Parser(_, pos) if pos.line > 0 => {
+                let message = e.to_string();
+                let line = self.text.lines().nth(pos.line - 1).unwrap_or("");
+                let number = pos.line.to_string();
+                let gutter = " ".repeat(number.len());
+                let caret = line
+                    .bytes()
+                    .take(pos.col - 1)
+                    .map(|b| if b == b'\t' { '\t' } else { ' ' })
+                    .collect::<String>();
+
+                eprintln!("error: {}", message.trim_start_matches(&format!("{}: ", pos)));
+                eprintln!("{}--> {}:{}:{}", gutter, self.name, pos.line, pos.col);
+                eprintln!("{} |", gutter);
+                eprintln!("{} | {}", number, line);
+                eprintln!("{} | {}^", gutter, caret);
+            }
+            ref e => eprintln!("error: {}: {}", self.name, e),
+        }
+
+        process::exit(1);
+    }
+
+    fn fail_with(&self, e: &dyn Display) -> !
+    {
+        eprintln!("error: {}: {}", self.name, e);
+        process::exit(1);
+    }
+}
+
+fn main()
+{
+    let mut args = env::args().skip(1);
+    let command = args.next().unwrap_or_else(|| usage());
+    let path = match command.as_str() {
+        "get" => Some(args.next().unwrap_or_else(|| usage())),
+        "check" | "to-json" | "from-json" | "pretty" | "minify" => None,
+        "-h" | "--help" | "help" => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => usage(),
+    };
+    let file = args.next();
+
+    if args.next().is_some() {
+        usage();
+    }
+
+    let input = Input::read(file);
+
+    let output = match command.as_str() {
+        "check" => match rson::de::from_str::<Value>(&input.text) {
+            Ok(_) => return,
+            Err(e) => input.fail(&e),
+        },
+        "to-json" => {
+            let value = rson_to_json(&input.text).unwrap_or_else(|e| input.fail(&e));
+
+            serde_json::to_string_pretty(&value).unwrap_or_else(|e| input.fail_with(&e))
+        }
+        "from-json" => {
+            let value = serde_json::from_str(&input.text).unwrap_or_else(|e| input.fail_with(&e));
+
+            json_to_rson(&value).unwrap_or_else(|e| input.fail_with(&e))
+        }
+        "get" => {
+            let document = Document::parse(&input.text).unwrap_or_else(|e| input.fail(&e));
+
+            match document.get_text(path.as_ref().unwrap()) {
+                Ok(text) => text,
+                Err(document::Error::Parse(ref e)) => input.fail(e),
+                Err(e) => input.fail_with(&e),
+            }
+        }
+        "pretty" => rson::fmt::format(&input.text).unwrap_or_else(|e| input.fail(&e)),
+        "minify" => rson::fmt::minify(&input.text).unwrap_or_else(|e| input.fail(&e)),
+        _ => unreachable!(),
+    };
+
+    if output.ends_with('\n') {
+        print!("{}", output);
+    } else {
+        println!("{}", output);
+    }
+}
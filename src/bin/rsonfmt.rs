@@ -0,0 +1,109 @@
+//! Formats RSON files in place.
+//!
+//! ```text
+//! rsonfmt [--check] [FILE]...
+//! ```
+//!
+//! Without files, stdin is formatted to stdout. With `--check`, nothing is
+//! written and the exit status is 1 if any input isn't formatted. Files
+//! which can't be read or parsed are reported and skipped, and make the
+//! exit status 2.
+
+extern crate rson_rs;
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use rson_rs::fmt::format;
+
+const USAGE: &str = "Usage: rsonfmt [--check] [FILE]...";
+
+/// Formats `input`, returning whether it was already formatted, or `None`
+/// after reporting an error.
+fn run(name: &str, input: &str, check: bool, write: &mut dyn FnMut(&str) -> io::Result<()>) -> Option<bool>
+{
+    let formatted = match format(input) {
+        Ok(formatted) => formatted,
+        Err(e) => {
+            eprintln!("{}: {}", name, e);
+            return None;
+        }
+    };
+
+    if formatted == input {
+        return Some(true);
+    }
+
+    if check {
+        println!("{} is not formatted", name);
+    } else if let Err(e) = write(&formatted) {
+        eprintln!("{}: {}", name, e);
+        return None;
+    }
+
+    Some(false)
+}
+
+fn main()
+{
+    let mut check = false;
+    let mut files = Vec::new();
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("Unknown option `{}`\n{}", arg, USAGE);
+                process::exit(2);
+            }
+            _ => files.push(arg),
+        }
+    }
+
+    let mut formatted = true;
+    let mut failed = false;
+
+    if files.is_empty() {
+        let mut input = String::new();
+
+        if let Err(e) = io::stdin().read_to_string(&mut input) {
+            eprintln!("<stdin>: {}", e);
+            process::exit(2);
+        }
+
+        match run("<stdin>", &input, check, &mut |s| io::stdout().write_all(s.as_bytes())) {
+            Some(true) if !check => print!("{}", input),
+            Some(result) => formatted = result,
+            None => failed = true,
+        }
+    }
+
+    for file in &files {
+        let result = match fs::read_to_string(file) {
+            Ok(input) => run(file, &input, check, &mut |s| fs::write(file, s)),
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                None
+            }
+        };
+
+        match result {
+            Some(result) => formatted &= result,
+            None => failed = true,
+        }
+    }
+
+    if failed {
+        process::exit(2);
+    }
+
+    if check && !formatted {
+        process::exit(1);
+    }
+}
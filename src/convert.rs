@@ -0,0 +1,432 @@
+//! Lossless conversion between RSON and JSON.
+//!
+//! JSON has no enums, tuples, chars or struct names, so these are
+//! represented as objects with keys starting with `$`, which can't
+//! clash with struct fields:
+//!
+//! | RSON                  | JSON                                          |
+//! |-----------------------|-----------------------------------------------|
+//! | `true`, `1.5`, `"s"`  | `true`, `1.5`, `"s"`                          |
+//! | `None`                | `null`                                        |
+//! | `'c'`                 | `{"$char": "c"}`                              |
+//! | `[a, b]`              | `[a, b]`                                      |
+//! | `()`, `(a, b)`        | `{"$tuple": []}`, `{"$tuple": [a, b]}`        |
+//! | `Variant`             | `{"$enum": "Variant"}`                        |
+//! | `Variant(a, b)`       | `{"$enum": "Variant", "$tuple": [a, b]}`      |
+//! | `{ x: a }`            | `{"x": a}`                                    |
+//! | `Name { x: a }`       | `{"$struct": "Name", "x": a}`                 |
+//! | `{ "k": a }`          | `{"$map": {"k": a}}`                          |
+//! | `{ 1: a }`            | `{"$map": [[1, a]]}`                          |
+//! | `include!("file")`    | `{"$include": "file"}`                        |
+//!
+//! `Some(a)` is the variant `Some` with one argument. Maps use the object
+//! form if all their keys are strings and the pair form otherwise.
+//!
+//! Converting RSON to JSON and back gives the same value. JSON which
+//! doesn't come from RSON is converted as well; objects whose keys are
+//! not all identifiers become maps.
+//!
+//! References to `let` bindings are replaced by the values of the
+//! bindings, which are not kept.
+//!
+//! Object keys are sorted unless the `preserve_order` feature is
+//! enabled, which keeps struct fields and map entries in their order.
+//!
+//! ```
+//! use rson_rs::convert::{json_to_rson, rson_to_json};
+//!
+//! let json = rson_to_json("Some(Point { x: 1, y: 2 })").unwrap();
+//! assert_eq!(
+//!     json.to_string(),
+//!     r#"{"$enum":"Some","$tuple":[{"$struct":"Point","x":1,"y":2}]}"#,
+//! );
+//! assert_eq!(
+//!     json_to_rson(&json).unwrap(),
+//!     "Some(\n    Point {\n        x: 1,\n        y: 2,\n    },\n)\n",
+//! );
+//! ```
+
+use serde_json::{Map, Number, Value};
+
+use cst::{self, Child, Node, NodeKind, Token, TokenKind};
+use de;
+use parse::{is_identifier, Bytes, ParsedStr};
+use ser;
+
+const CHAR: &str = "$char";
+const ENUM: &str = "$enum";
+const INCLUDE: &str = "$include";
+const MAP: &str = "$map";
+const STRUCT: &str = "$struct";
+const TUPLE: &str = "$tuple";
+
+/// Converts RSON text to a JSON value.
+pub fn rson_to_json(input: &str) -> de::Result<Value>
+{
+    let document = cst::parse(input)?;
+    let mut bindings = Vec::new();
+
+    cst::check_expansion(&document)?;
+
+    for binding in document.bindings() {
+        let value = node_to_json(binding.value().expect("Bug: binding without value"), &bindings)?;
+
+        bindings.push((binding.name().expect("Bug: binding without name"), value));
+    }
+
+    node_to_json(document.value().expect("Bug: document without value"), &bindings)
+}
+
+/// Converts a JSON value to formatted RSON text.
+pub fn json_to_rson(value: &Value) -> ser::Result<String>
+{
+    let mut out = String::new();
+    write_rson(value, &mut out)?;
+
+    Ok(::fmt::format(&out).expect("Bug: produced invalid RSON"))
+}
+
+/// Converts `node`, replacing references to `bindings` by their values.
+fn node_to_json(node: &Node, bindings: &[(&str, Value)]) -> de::Result<Value>
+{
+    let items = || node.items().map(|n| node_to_json(n, bindings)).collect::<de::Result<Vec<_>>>();
+
+    let value = match node.kind() {
+        NodeKind::Literal => {
+            let token = node.token().expect("Bug: empty literal");
+            let reference = bindings
+                .iter()
+                .rev()
+                .find(|b| token.kind() == TokenKind::Ident && b.0 == token.text());
+
+            match reference {
+                Some((_, value)) => value.clone(),
+                None => literal_to_json(token)?,
+            }
+        }
+        NodeKind::Seq => Value::Array(items()?),
+        NodeKind::Tuple => {
+            let mut object = Map::new();
+
+            if let Some(name) = node.name() {
+                object.insert(ENUM.to_owned(), Value::String(name.to_owned()));
+            }
+
+            object.insert(TUPLE.to_owned(), Value::Array(items()?));
+
+            Value::Object(object)
+        }
+        NodeKind::Struct => {
+            let mut object = Map::new();
+
+            if let Some(name) = node.name() {
+                object.insert(STRUCT.to_owned(), Value::String(name.to_owned()));
+            }
+
+            for entry in node.items() {
+                let key = entry.key().and_then(Child::as_token).expect("Bug: struct key");
+                let value = node_to_json(entry.value().expect("Bug: entry without value"), bindings)?;
+
+                object.insert(key.text().to_owned(), value);
+            }
+
+            Value::Object(object)
+        }
+        NodeKind::Map => {
+            let mut pairs = Vec::new();
+
+            for entry in node.items() {
+                let key = entry.key().and_then(Child::as_node).expect("Bug: map key");
+                let value = entry.value().expect("Bug: entry without value");
+
+                // Map keys aren't references.
+                let key = match key.kind() {
+                    NodeKind::Literal => node_to_json(key, &[])?,
+                    _ => node_to_json(key, bindings)?,
+                };
+
+                pairs.push((key, node_to_json(value, bindings)?));
+            }
+
+            let map = if pairs.iter().all(|(k, _)| k.is_string()) {
+                Value::Object(
+                    pairs
+                        .into_iter()
+                        .map(|(k, v)| (k.as_str().unwrap().to_owned(), v))
+                        .collect(),
+                )
+            } else {
+                Value::Array(pairs.into_iter().map(|(k, v)| Value::Array(vec![k, v])).collect())
+            };
+
+            let mut object = Map::new();
+            object.insert(MAP.to_owned(), map);
+
+            Value::Object(object)
+        }
+        NodeKind::Include => {
+            let file = node.tokens().find(|t| t.kind() == TokenKind::String).expect("Bug: include without file");
+
+            tagged(INCLUDE, literal_to_json(file)?)
+        }
+        NodeKind::Document | NodeKind::Entry | NodeKind::Binding => unreachable!("Bug: not a value"),
+    };
+
+    Ok(value)
+}
+
+fn literal_to_json(token: &Token) -> de::Result<Value>
+{
+    let text = token.text();
+    let mut bytes = Bytes::new(text.as_bytes());
+
+    let value = match token.kind() {
+        TokenKind::Ident => match text {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            "None" => Value::Null,
+            _ => tagged(ENUM, Value::String(text.to_owned())),
+        },
+        TokenKind::Number => {
+            let number = if let Ok(n) = text.parse::<u64>() {
+                Number::from(n)
+            } else if let Ok(n) = text.parse::<i64>() {
+                Number::from(n)
+            } else {
+                Number::from_f64(bytes.float()?).ok_or_else(|| {
+                    de::Error::Message(format!("`{}` can't be represented in JSON", text))
+                })?
+            };
+
+            Value::Number(number)
+        }
+        TokenKind::String => Value::String(match bytes.string()? {
+            ParsedStr::Allocated(s) => s,
+            ParsedStr::Slice(s) => s.to_owned(),
+        }),
+        TokenKind::Char => tagged(CHAR, Value::String(bytes.char()?.to_string())),
+        _ => unreachable!("Bug: not a literal"),
+    };
+
+    Ok(value)
+}
+
+fn tagged(tag: &str, value: Value) -> Value
+{
+    let mut object = Map::new();
+    object.insert(tag.to_owned(), value);
+
+    Value::Object(object)
+}
+
+fn invalid(value: &Value) -> ser::Error
+{
+    ser::Error::Message(format!("Invalid tagged object: {}", value))
+}
+
+fn write_rson(value: &Value, out: &mut String) -> ser::Result<()>
+{
+    match *value {
+        Value::Null => out.push_str("None"),
+        Value::Bool(b) => out.push_str(if b { "true" } else { "false" }),
+        Value::Number(ref n) => out.push_str(&n.to_string()),
+        Value::String(ref s) => out.push_str(&ser::to_string(s)?),
+        Value::Array(ref items) => write_items("[", items, "]", out)?,
+        Value::Object(ref object) => write_object(value, object, out)?,
+    }
+
+    Ok(())
+}
+
+fn write_items(open: &str, items: &[Value], close: &str, out: &mut String) -> ser::Result<()>
+{
+    out.push_str(open);
+
+    for item in items {
+        write_rson(item, out)?;
+        out.push(',');
+    }
+
+    out.push_str(close);
+
+    Ok(())
+}
+
+/// Checks if `name` can be written as the name of an enum variant or a
+/// struct, which excludes identifiers with a meaning of their own.
+fn is_name(name: &str) -> bool
+{
+    is_identifier(name) && !["true", "false", "None"].contains(&name)
+}
+
+fn write_object(value: &Value, object: &Map<String, Value>, out: &mut String) -> ser::Result<()>
+{
+    let get_name = |key| {
+        object.get(key).map(|v| v.as_str().filter(|&name| is_name(name)).ok_or_else(|| invalid(value)))
+    };
+
+    if let Some(c) = object.get(CHAR) {
+        let c = match c.as_str().map(|s| (s.chars().count(), s)) {
+            Some((1, s)) if object.len() == 1 => s.chars().next().unwrap(),
+            _ => return Err(invalid(value)),
+        };
+
+        out.push_str(&ser::to_string(&c)?);
+    } else if let Some(file) = object.get(INCLUDE) {
+        match *file {
+            Value::String(ref file) if object.len() == 1 => {
+                out.push_str("include!(");
+                out.push_str(&ser::to_string(file)?);
+                out.push(')');
+            }
+            _ => return Err(invalid(value)),
+        }
+    } else if let Some(map) = object.get(MAP) {
+        if object.len() != 1 {
+            return Err(invalid(value));
+        }
+
+        out.push('{');
+
+        match *map {
+            Value::Object(ref map) => for (k, v) in map {
+                out.push_str(&ser::to_string(k)?);
+                out.push(':');
+                write_rson(v, out)?;
+                out.push(',');
+            },
+            Value::Array(ref pairs) => for pair in pairs {
+                match pair.as_array().map(Vec::as_slice) {
+                    Some([k, v]) => {
+                        write_rson(k, out)?;
+                        out.push(':');
+                        write_rson(v, out)?;
+                        out.push(',');
+                    }
+                    _ => return Err(invalid(value)),
+                }
+            },
+            _ => return Err(invalid(value)),
+        }
+
+        out.push('}');
+    } else if object.contains_key(ENUM) || object.contains_key(TUPLE) {
+        let name = get_name(ENUM).transpose()?.unwrap_or("");
+
+        out.push_str(name);
+
+        match object.get(TUPLE) {
+            Some(Value::Array(items)) if object.len() == 1 + !name.is_empty() as usize => {
+                write_items("(", items, ")", out)?;
+            }
+            None if object.len() == 1 => {}
+            _ => return Err(invalid(value)),
+        }
+    } else if object.keys().all(|k| k == STRUCT || is_identifier(k)) {
+        if let Some(name) = get_name(STRUCT).transpose()? {
+            out.push_str(name);
+        }
+
+        out.push('{');
+
+        for (k, v) in object.iter().filter(|&(k, _)| k != STRUCT) {
+            out.push_str(k);
+            out.push(':');
+            write_rson(v, out)?;
+            out.push(',');
+        }
+
+        out.push('}');
+    } else {
+        let mut map = Map::new();
+        map.insert(MAP.to_owned(), Value::Object(object.clone()));
+
+        write_rson(&Value::Object(map), out)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn roundtrip(rson: &str, json: &str)
+    {
+        let value = rson_to_json(rson).expect("Failed to convert to JSON");
+
+        assert_eq!(value.to_string(), json);
+        assert_eq!(json_to_rson(&value).unwrap(), ::fmt::format(rson).unwrap());
+    }
+
+    #[test]
+    fn test_roundtrip()
+    {
+        roundtrip("[true, None, 1, -2, 2.5, \"a \\\"b\\\"\", 'c']", r#"[true,null,1,-2,2.5,"a \"b\"",{"$char":"c"}]"#);
+        roundtrip("['é', '🦀']", r#"[{"$char":"é"},{"$char":"🦀"}]"#);
+        roundtrip("[(), (1, 2), Yellow, Custom(0.5), Some(None)]", concat!(
+            r#"[{"$tuple":[]},{"$tuple":[1,2]},{"$enum":"Yellow"},"#,
+            r#"{"$enum":"Custom","$tuple":[0.5]},{"$enum":"Some","$tuple":[null]}]"#,
+        ));
+        roundtrip("Level {\n    size: (10, 20),\n    tags: {\n        \"$x\": [],\n    },\n}",
+            r#"{"$struct":"Level","size":{"$tuple":[10,20]},"tags":{"$map":{"$x":[]}}}"#);
+        roundtrip("{\n    1: {},\n    'a': Empty {},\n}", r#"{"$map":[[1,{"$map":{}}],[{"$char":"a"},{"$struct":"Empty"}]]}"#);
+        roundtrip("{\n    a: include!(\"a.rson\"),\n}", r#"{"a":{"$include":"a.rson"}}"#);
+    }
+
+    #[test]
+    fn test_plain_json()
+    {
+        let value = ::serde_json::from_str(r#"{"a b": 1.0, "list": [{"id": 3}], "name": "x"}"#).unwrap();
+
+        assert_eq!(
+            json_to_rson(&value).unwrap(),
+            "{\n    \"a b\": 1.0,\n    \"list\": [\n        {\n            id: 3,\n        },\n    ],\n    \"name\": \"x\",\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_bindings()
+    {
+        let json = rson_to_json("let a = 'a';\nlet b = [a, a];\n{ \"a\": b, a: 1 }").unwrap();
+        assert_eq!(json.to_string(), r#"{"$map":[["a",[{"$char":"a"},{"$char":"a"}]],[{"$enum":"a"},1]]}"#);
+
+        let mut doubling = "let a0 = [1, 1];".to_owned();
+
+        for i in 1..24 {
+            doubling += &format!("let a{} = [a{1}, a{1}];", i, i - 1);
+        }
+
+        match rson_to_json(&(doubling + "a23")) {
+            Err(de::Error::Parser(de::ParseError::ExpansionLimit, _)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_invalid()
+    {
+        let invalid = [
+            r#"{"$char": "ab"}"#,
+            r#"{"$enum": 1}"#,
+            r#"{"$enum": "A", "x": 1}"#,
+            r#"{"$map": [[1]]}"#,
+            r#"{"$struct": "not a name"}"#,
+            r#"{"$struct": "X", "$tuple": []}"#,
+            r#"{"$struct": "X", "$enum": "Y"}"#,
+            r#"{"$char": "c", "x": 1}"#,
+            r#"{"$enum": "true"}"#,
+            r#"{"$enum": "None", "$tuple": [1]}"#,
+            r#"{"$struct": "false", "x": 1}"#,
+            r#"{"$include": 1}"#,
+            r#"{"$include": "a", "x": 1}"#,
+        ];
+
+        for json in &invalid {
+            let value = ::serde_json::from_str(json).unwrap();
+
+            assert!(json_to_rson(&value).is_err(), "{} was accepted", json);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::str::FromStr;
+
+use de::{Error, ParseError, Result};
+use parse::{Bytes, Position, FLOAT_CHARS, IDENT_CHAR, IDENT_FIRST, WHITE_SPACE};
+
+use super::{Token, TokenKind};
+
+/// Splits `input` into tokens, trivia included, along with
+/// the position each token starts at.
+pub fn tokenize(input: &str) -> Result<Vec<(Token, Position)>>
+{
+    let mut tokens = Vec::new();
+    let mut rest = input;
+    let mut pos = Position { line: 1, col: 1 };
+
+    while !rest.is_empty() {
+        let (kind, len) = next_token(rest).map_err(|kind| Error::Parser(kind, pos))?;
+        let text = &rest[..len];
+
+        validate(kind, text).map_err(|kind| Error::Parser(kind, pos))?;
+
+        tokens.push((Token::new(kind, text), pos));
+        pos.advance(text.as_bytes());
+        rest = &rest[len..];
+    }
+
+    Ok(tokens)
+}
+
+/// Returns the kind and the length of the token `input` starts with.
+pub(crate) fn next_token(input: &str) -> ::std::result::Result<(TokenKind, usize), ParseError>
+{
+    let bytes = input.as_bytes();
+    let first = bytes[0];
+    let second = bytes.get(1).cloned();
+
+    let token = match first {
+        b'{' => (TokenKind::OpenBrace, 1),
+        b'}' => (TokenKind::CloseBrace, 1),
+        b'(' => (TokenKind::OpenParen, 1),
+        b')' => (TokenKind::CloseParen, 1),
+        b'[' => (TokenKind::OpenBracket, 1),
+        b']' => (TokenKind::CloseBracket, 1),
+        b',' => (TokenKind::Comma, 1),
+        b':' => (TokenKind::Colon, 1),
+        b'!' => (TokenKind::Bang, 1),
+        b'=' => (TokenKind::Equals, 1),
+        b';' => (TokenKind::Semicolon, 1),
+        b'/' if second == Some(b'/') => {
+            let len = bytes.iter().position(|&b| b == b'\n').unwrap_or(bytes.len());
+
+            (TokenKind::LineComment, len)
+        }
+        b'/' if second == Some(b'*') => (TokenKind::BlockComment, block_comment(bytes)?),
+        b'r' if second == Some(b'"') || second == Some(b'#') => {
+            (TokenKind::String, raw_string(bytes)?)
+        }
+        b'"' => (TokenKind::String, string(bytes)?),
+        b'\'' => (TokenKind::Char, char(input)?),
+        b'0' ..= b'9' | b'+' | b'-' | b'.' => (TokenKind::Number, run(bytes, FLOAT_CHARS)),
+        b if WHITE_SPACE.contains(&b) => (TokenKind::Whitespace, run(bytes, WHITE_SPACE)),
+        b if IDENT_FIRST.contains(&b) => (TokenKind::Ident, run(bytes, IDENT_CHAR)),
+        _ => {
+            let c = input.chars().next().unwrap_or('\0');
+
+            return Err(ParseError::UnexpectedByte(c));
+        }
+    };
+
+    Ok(token)
+}
+
+fn run(bytes: &[u8], allowed: &[u8]) -> usize
+{
+    bytes.iter().take_while(|b| allowed.contains(b)).count()
+}
+
+/// Block comments nest, so every `/*` has to be matched by a `*/`.
+fn block_comment(bytes: &[u8]) -> ::std::result::Result<usize, ParseError>
+{
+    let mut level = 0;
+    let mut i = 0;
+
+    while i + 1 < bytes.len() {
+        match (bytes[i], bytes[i + 1]) {
+            (b'/', b'*') => {
+                level += 1;
+                i += 2;
+            }
+            (b'*', b'/') => {
+                level -= 1;
+                i += 2;
+
+                if level == 0 {
+                    return Ok(i);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    Err(ParseError::Eof)
+}
+
+fn string(bytes: &[u8]) -> ::std::result::Result<usize, ParseError>
+{
+    let mut i = 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Ok(i + 1),
+            _ => i += 1,
+        }
+    }
+
+    Err(ParseError::Eof)
+}
+
+fn raw_string(bytes: &[u8]) -> ::std::result::Result<usize, ParseError>
+{
+    let num_hashes = bytes[1..].iter().take_while(|&&b| b == b'#').count();
+    let start = 1 + num_hashes;
+
+    if bytes.get(start) != Some(&b'"') {
+        return Err(ParseError::ExpectedString);
+    }
+
+    let ending = [&b"\""[..], &bytes[1..start]].concat();
+
+    bytes[start + 1..]
+        .windows(ending.len())
+        .position(|window| window == ending.as_slice())
+        .map(|i| start + 1 + i + ending.len())
+        .ok_or(ParseError::ExpectedStringEnd)
+}
+
+fn char(input: &str) -> ::std::result::Result<usize, ParseError>
+{
+    let mut chars = input.char_indices().skip(1);
+
+    let end = match chars.next() {
+        Some((_, '\\')) => chars.nth(1),
+        Some(_) => chars.next(),
+        None => None,
+    };
+
+    match end {
+        Some((i, '\'')) => Ok(i + 1),
+        _ => Err(ParseError::ExpectedChar),
+    }
+}
+
+/// Checks that literal tokens can actually be read by the deserializer.
+fn validate(kind: TokenKind, text: &str) -> ::std::result::Result<(), ParseError>
+{
+    let res = match kind {
+        TokenKind::String => Bytes::new(text.as_bytes()).string().map(|_| ()),
+        TokenKind::Number => {
+            return f64::from_str(text).map(|_| ()).map_err(|_| ParseError::ExpectedFloat);
+        }
+        _ => Ok(()),
+    };
+
+    res.map_err(|e| match e {
+        Error::Parser(kind, _) => kind,
+        _ => ParseError::ExpectedString,
+    })
+}
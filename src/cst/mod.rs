@@ -0,0 +1,785 @@
+//! Lossless concrete syntax tree.
+//!
+//! Unlike the deserializer, which throws comments and whitespace away,
+//! the tree produced by `parse` keeps every byte of the input as a token,
+//! so printing it gives back exactly the text it was parsed from.
+//! This makes it the base for tools which need to rewrite RSON files
+//! without destroying the comments in them.
+//!
+//! ```
+//! use rson_rs::cst::{parse, NodeKind};
+//!
+//! let source = "{ // the origin\n    x: 0, y: 0 }";
+//! let tree = parse(source).unwrap();
+//!
+//! assert_eq!(tree.value().unwrap().kind(), NodeKind::Struct);
+//! assert_eq!(tree.to_string(), source);
+//! ```
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use de::{Error, ParseError, Result, MAX_EXPANSION};
+use parse::Position;
+
+pub(crate) mod lexer;
+#[cfg(test)]
+mod tests;
+
+/// The kind of a token.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TokenKind
+{
+    /// A run of spaces, tabs and newlines.
+    Whitespace,
+    /// A `//` comment, not including the terminating newline.
+    LineComment,
+    /// A (possibly nested) `/* */` comment.
+    BlockComment,
+    /// An identifier, including `true`, `false` and `None`.
+    Ident,
+    /// An integer or float literal, including its sign.
+    Number,
+    /// A string literal, escaped or raw.
+    String,
+    /// A char literal.
+    Char,
+    OpenBrace,
+    CloseBrace,
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Colon,
+    /// The `!` of `include!`.
+    Bang,
+    /// The `=` of a `let` binding.
+    Equals,
+    /// The `;` ending a `let` binding.
+    Semicolon,
+}
+
+impl TokenKind
+{
+    /// Trivia are tokens without any meaning for the data:
+    /// whitespace and comments.
+    pub fn is_trivia(self) -> bool
+    {
+        matches!(self, TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment)
+    }
+
+    /// Returns true for line and block comments.
+    pub fn is_comment(self) -> bool
+    {
+        self == TokenKind::LineComment || self == TokenKind::BlockComment
+    }
+}
+
+/// A single token together with its source text.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Token
+{
+    kind: TokenKind,
+    text: String,
+}
+
+impl Token
+{
+    /// Creates a token; `text` is expected to be valid for `kind`.
+    pub fn new(kind: TokenKind, text: &str) -> Self
+    {
+        Token {
+            kind,
+            text: text.to_owned(),
+        }
+    }
+
+    pub fn kind(&self) -> TokenKind
+    {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str
+    {
+        &self.text
+    }
+}
+
+impl Display for Token
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        f.write_str(&self.text)
+    }
+}
+
+/// The kind of a node.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NodeKind
+{
+    /// The root: the value surrounded by leading and trailing trivia,
+    /// after any `let` bindings.
+    Document,
+    /// A single literal token: number, string, char or a bare identifier.
+    Literal,
+    /// `Name { field: value, .. }` with an optional name.
+    Struct,
+    /// `{ key: value, .. }`.
+    Map,
+    /// `Name(value, ..)` with an optional name; this covers tuples,
+    /// tuple structs, enum variants and `Some(..)`.
+    Tuple,
+    /// `[value, ..]`.
+    Seq,
+    /// A `key: value` pair inside of a struct or map.
+    Entry,
+    /// `include!("file")`, which stands for the content of another file.
+    Include,
+    /// `let name = value;` at the start of a document.
+    Binding,
+}
+
+/// A child of a node: either a token or another node.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Child
+{
+    Token(Token),
+    Node(Node),
+}
+
+impl Child
+{
+    pub fn as_token(&self) -> Option<&Token>
+    {
+        match *self {
+            Child::Token(ref t) => Some(t),
+            Child::Node(_) => None,
+        }
+    }
+
+    pub fn as_node(&self) -> Option<&Node>
+    {
+        match *self {
+            Child::Token(_) => None,
+            Child::Node(ref n) => Some(n),
+        }
+    }
+
+    pub fn as_node_mut(&mut self) -> Option<&mut Node>
+    {
+        match *self {
+            Child::Token(_) => None,
+            Child::Node(ref mut n) => Some(n),
+        }
+    }
+
+    /// Returns true if this is a whitespace or comment token.
+    pub fn is_trivia(&self) -> bool
+    {
+        self.as_token().map(|t| t.kind().is_trivia()).unwrap_or(false)
+    }
+
+    fn write(&self, out: &mut String)
+    {
+        match *self {
+            Child::Token(ref t) => out.push_str(t.text()),
+            Child::Node(ref n) => n.write(out),
+        }
+    }
+}
+
+/// A node of the syntax tree.
+///
+/// The text of a node is the concatenation of the text of its children.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Node
+{
+    kind: NodeKind,
+    children: Vec<Child>,
+}
+
+impl Node
+{
+    pub fn new(kind: NodeKind, children: Vec<Child>) -> Self
+    {
+        Node { kind, children }
+    }
+
+    pub fn kind(&self) -> NodeKind
+    {
+        self.kind
+    }
+
+    pub fn children(&self) -> &[Child]
+    {
+        &self.children
+    }
+
+    /// Gives direct access to the children.
+    ///
+    /// It's up to the caller to keep the tree well-formed.
+    pub fn children_mut(&mut self) -> &mut Vec<Child>
+    {
+        &mut self.children
+    }
+
+    /// Iterates over the direct child nodes, skipping tokens.
+    pub fn nodes(&self) -> impl Iterator<Item = &Node>
+    {
+        self.children.iter().filter_map(Child::as_node)
+    }
+
+    /// Iterates over the direct child tokens, skipping nodes.
+    pub fn tokens(&self) -> impl Iterator<Item = &Token>
+    {
+        self.children.iter().filter_map(Child::as_token)
+    }
+
+    /// Returns the single token of a `Literal`.
+    pub fn token(&self) -> Option<&Token>
+    {
+        match self.kind {
+            NodeKind::Literal => self.tokens().next(),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of a `Struct` or `Tuple`, if it has one,
+    /// or the name of a `Binding`.
+    pub fn name(&self) -> Option<&str>
+    {
+        match self.kind {
+            NodeKind::Struct | NodeKind::Tuple => self
+                .tokens()
+                .next()
+                .filter(|t| t.kind() == TokenKind::Ident)
+                .map(Token::text),
+            NodeKind::Binding => self
+                .tokens()
+                .filter(|t| t.kind() == TokenKind::Ident)
+                .nth(1)
+                .map(Token::text),
+            _ => None,
+        }
+    }
+
+    /// Returns the key of an `Entry`: an identifier token for struct
+    /// fields and a value node for map keys.
+    pub fn key(&self) -> Option<&Child>
+    {
+        match self.kind {
+            NodeKind::Entry => self.children.first(),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a `Document`, an `Entry` or a `Binding`.
+    pub fn value(&self) -> Option<&Node>
+    {
+        match self.kind {
+            NodeKind::Document | NodeKind::Entry | NodeKind::Binding => self.nodes().last(),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a `Document`, an `Entry` or a `Binding` mutably.
+    pub fn value_mut(&mut self) -> Option<&mut Node>
+    {
+        match self.kind {
+            NodeKind::Document | NodeKind::Entry | NodeKind::Binding => self
+                .children
+                .iter_mut()
+                .filter_map(Child::as_node_mut)
+                .last(),
+            _ => None,
+        }
+    }
+
+    /// Iterates over the `let` bindings of a `Document`.
+    pub fn bindings(&self) -> impl Iterator<Item = &Node>
+    {
+        self.nodes().filter(|n| n.kind() == NodeKind::Binding)
+    }
+
+    /// Iterates over the items of a collection: entries of structs
+    /// and maps, or values of tuples and sequences.
+    pub fn items(&self) -> impl Iterator<Item = &Node>
+    {
+        let is_collection = matches!(
+            self.kind,
+            NodeKind::Struct | NodeKind::Map | NodeKind::Tuple | NodeKind::Seq
+        );
+
+        self.nodes().filter(move |_| is_collection)
+    }
+
+    /// Returns the source text of this node.
+    pub fn text(&self) -> String
+    {
+        let mut out = String::new();
+        self.write(&mut out);
+
+        out
+    }
+
+    fn write(&self, out: &mut String)
+    {
+        for child in &self.children {
+            child.write(out);
+        }
+    }
+}
+
+impl Display for Node
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        f.write_str(&self.text())
+    }
+}
+
+/// Parses `input` into a `Document` node.
+///
+/// Printing the returned node gives back `input` byte for byte.
+pub fn parse(input: &str) -> Result<Node>
+{
+    let tokens = lexer::tokenize(input)?;
+    let mut end = Position { line: 1, col: 1 };
+    end.advance(input.as_bytes());
+
+    let mut parser = Parser {
+        tokens: tokens.into_iter(),
+        peeked: Vec::new(),
+        end,
+    };
+
+    parser.document()
+}
+
+/// Parses `input` as a single value without surrounding trivia,
+/// as needed when splicing new values into an existing tree.
+pub fn parse_value(input: &str) -> Result<Node>
+{
+    let mut document = parse(input)?;
+    let index = document
+        .children
+        .iter()
+        .rposition(|c| c.as_node().is_some())
+        .expect("Bug: document without a value");
+
+    match document.children.swap_remove(index) {
+        Child::Node(n) => Ok(n),
+        Child::Token(_) => unreachable!(),
+    }
+}
+
+/// Checks that the references to the `let` bindings of `document` don't
+/// expand to more than the deserializer accepts, before tools copy the
+/// values of bindings.
+pub(crate) fn check_expansion(document: &Node) -> Result<()>
+{
+    let mut pos = Position { line: 1, col: 1 };
+    let mut bindings = Vec::new();
+
+    for child in document.children() {
+        match *child {
+            Child::Token(ref t) => pos.advance(t.text().as_bytes()),
+            Child::Node(ref n) if n.kind() == NodeKind::Binding => {
+                let mut len = 0;
+
+                for child in n.children() {
+                    match *child {
+                        Child::Token(ref t) => pos.advance(t.text().as_bytes()),
+                        Child::Node(ref value) => len = expanded_len(value, &bindings, &mut pos)?,
+                    }
+                }
+
+                bindings.push((n.name().expect("Bug: binding without name"), len));
+            }
+            Child::Node(ref n) => {
+                expanded_len(n, &bindings, &mut pos)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the length of `node` with references to `bindings` replaced
+/// by their expanded length, advancing `pos` past the node.
+fn expanded_len(node: &Node, bindings: &[(&str, usize)], pos: &mut Position) -> Result<usize>
+{
+    let start = *pos;
+    let mut len = 0;
+
+    for (i, child) in node.children().iter().enumerate() {
+        len += match *child {
+            Child::Token(ref t) => {
+                let reference = match node.kind() {
+                    NodeKind::Literal if t.kind() == TokenKind::Ident => {
+                        bindings.iter().rev().find(|b| b.0 == t.text()).map(|b| b.1)
+                    }
+                    _ => None,
+                };
+
+                pos.advance(t.text().as_bytes());
+
+                reference.unwrap_or_else(|| t.text().len())
+            }
+            // Map keys aren't references.
+            Child::Node(ref n) if node.kind() == NodeKind::Entry && i == 0 && n.kind() == NodeKind::Literal => {
+                let text = n.text();
+                pos.advance(text.as_bytes());
+
+                text.len()
+            }
+            Child::Node(ref n) => expanded_len(n, bindings, pos)?,
+        };
+
+        if len > MAX_EXPANSION {
+            return Err(Error::Parser(ParseError::ExpansionLimit, start));
+        }
+    }
+
+    Ok(len)
+}
+
+struct Parser
+{
+    tokens: ::std::vec::IntoIter<(Token, Position)>,
+    peeked: Vec<(Token, Position)>,
+    end: Position,
+}
+
+impl Parser
+{
+    /// Returns the `n`th upcoming token which is not trivia.
+    fn peek_significant(&mut self, n: usize) -> Option<&(Token, Position)>
+    {
+        let mut seen = 0;
+        let mut i = 0;
+
+        loop {
+            if i == self.peeked.len() {
+                let next = self.tokens.next()?;
+                self.peeked.push(next);
+            }
+
+            if !self.peeked[i].0.kind().is_trivia() {
+                if seen == n {
+                    return Some(&self.peeked[i]);
+                }
+
+                seen += 1;
+            }
+
+            i += 1;
+        }
+    }
+
+    fn peek_kind(&mut self, n: usize) -> Option<TokenKind>
+    {
+        self.peek_significant(n).map(|t| t.0.kind())
+    }
+
+    fn position(&mut self) -> Position
+    {
+        let end = self.end;
+
+        self.peek_significant(0).map(|t| t.1).unwrap_or(end)
+    }
+
+    fn err<T>(&mut self, kind: ParseError) -> Result<T>
+    {
+        Err(Error::Parser(kind, self.position()))
+    }
+
+    fn next_raw(&mut self) -> Option<Token>
+    {
+        if self.peeked.is_empty() {
+            self.tokens.next().map(|t| t.0)
+        } else {
+            Some(self.peeked.remove(0).0)
+        }
+    }
+
+    /// Moves leading trivia into `children`.
+    fn trivia(&mut self, children: &mut Vec<Child>)
+    {
+        loop {
+            let is_trivia = match self.peeked.first() {
+                Some(t) => t.0.kind().is_trivia(),
+                None => match self.tokens.next() {
+                    Some(t) => {
+                        let is_trivia = t.0.kind().is_trivia();
+                        self.peeked.push(t);
+
+                        is_trivia
+                    }
+                    None => false,
+                },
+            };
+
+            if !is_trivia {
+                break;
+            }
+
+            let token = self.next_raw().expect("Bug: peeked token vanished");
+            children.push(Child::Token(token));
+        }
+    }
+
+    /// Moves trivia and the next significant token into `children`.
+    fn bump(&mut self, children: &mut Vec<Child>)
+    {
+        self.trivia(children);
+
+        if let Some(token) = self.next_raw() {
+            children.push(Child::Token(token));
+        }
+    }
+
+    fn document(&mut self) -> Result<Node>
+    {
+        let mut children = Vec::new();
+
+        self.trivia(&mut children);
+
+        while self.is_binding() {
+            children.push(Child::Node(self.binding()?));
+            self.trivia(&mut children);
+        }
+
+        children.push(Child::Node(self.value()?));
+        self.trivia(&mut children);
+
+        if self.peek_kind(0).is_some() {
+            return self.err(ParseError::TrailingCharacters);
+        }
+
+        Ok(Node::new(NodeKind::Document, children))
+    }
+
+    /// Checks if `let` and a name follow.
+    fn is_binding(&mut self) -> bool
+    {
+        let is_let = self.peek_significant(0).map(|t| t.0.text() == "let").unwrap_or(false);
+
+        is_let && self.peek_kind(1) == Some(TokenKind::Ident)
+    }
+
+    fn binding(&mut self) -> Result<Node>
+    {
+        let mut children = Vec::new();
+        self.bump(&mut children);
+        self.bump(&mut children);
+
+        if self.peek_kind(0) != Some(TokenKind::Equals) {
+            return self.err(ParseError::ExpectedBinding);
+        }
+
+        self.bump(&mut children);
+        self.trivia(&mut children);
+        children.push(Child::Node(self.value()?));
+
+        if self.peek_kind(0) != Some(TokenKind::Semicolon) {
+            return self.err(ParseError::ExpectedBinding);
+        }
+
+        self.bump(&mut children);
+
+        Ok(Node::new(NodeKind::Binding, children))
+    }
+
+    /// Parses a value, expecting any leading trivia to be consumed already.
+    fn value(&mut self) -> Result<Node>
+    {
+        let first = match self.peek_significant(0) {
+            Some(t) => t.0.clone(),
+            None => return self.err(ParseError::Eof),
+        };
+
+        match first.kind() {
+            TokenKind::Ident => match self.peek_kind(1) {
+                Some(TokenKind::OpenBrace) => self.braces(),
+                Some(TokenKind::OpenParen) => self.tuple(),
+                Some(TokenKind::Bang) => self.include(),
+                _ => self.literal(),
+            },
+            TokenKind::Number | TokenKind::String | TokenKind::Char => self.literal(),
+            TokenKind::OpenBrace => self.braces(),
+            TokenKind::OpenParen => self.tuple(),
+            TokenKind::OpenBracket => self.seq(),
+            _ => {
+                let c = first.text().chars().next().unwrap_or('\0');
+
+                self.err(ParseError::UnexpectedByte(c))
+            }
+        }
+    }
+
+    fn literal(&mut self) -> Result<Node>
+    {
+        let mut children = Vec::new();
+        self.bump(&mut children);
+
+        Ok(Node::new(NodeKind::Literal, children))
+    }
+
+    /// Parses `include!("file")`, where the `!` has to follow right after
+    /// the name.
+    fn include(&mut self) -> Result<Node>
+    {
+        let mut children = Vec::new();
+        let is_include = self.peek_significant(0).map(|t| t.0.text() == "include").unwrap_or(false);
+
+        self.bump(&mut children);
+
+        let adjacent = self.peek_kind(0) == Some(TokenKind::Bang) && self.peeked[0].0.kind() == TokenKind::Bang;
+
+        if !is_include || !adjacent {
+            return self.err(ParseError::ExpectedInclude);
+        }
+
+        for &kind in &[TokenKind::Bang, TokenKind::OpenParen, TokenKind::String, TokenKind::CloseParen] {
+            if self.peek_kind(0) != Some(kind) {
+                return self.err(ParseError::ExpectedInclude);
+            }
+
+            self.bump(&mut children);
+        }
+
+        Ok(Node::new(NodeKind::Include, children))
+    }
+
+    /// Consumes an optional name in front of a struct or a tuple.
+    fn name(&mut self, children: &mut Vec<Child>) -> bool
+    {
+        if self.peek_kind(0) == Some(TokenKind::Ident) {
+            self.bump(children);
+            self.trivia(children);
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn braces(&mut self) -> Result<Node>
+    {
+        let mut children = Vec::new();
+        let named = self.name(&mut children);
+
+        self.bump(&mut children);
+
+        let is_struct = match (self.peek_kind(0), self.peek_kind(1)) {
+            (Some(TokenKind::Ident), Some(TokenKind::Colon)) => true,
+            (Some(TokenKind::CloseBrace), _) => named,
+            _ => false,
+        };
+
+        if named && !is_struct {
+            return self.err(ParseError::ExpectedIdentifier);
+        }
+
+        if is_struct {
+            self.items(&mut children, TokenKind::CloseBrace, ParseError::ExpectedStructEnd, Parser::field)?;
+
+            Ok(Node::new(NodeKind::Struct, children))
+        } else {
+            self.items(&mut children, TokenKind::CloseBrace, ParseError::ExpectedMapEnd, Parser::entry)?;
+
+            Ok(Node::new(NodeKind::Map, children))
+        }
+    }
+
+    fn tuple(&mut self) -> Result<Node>
+    {
+        let mut children = Vec::new();
+        self.name(&mut children);
+        self.bump(&mut children);
+
+        self.items(&mut children, TokenKind::CloseParen, ParseError::ExpectedArrayEnd, Parser::value)?;
+
+        Ok(Node::new(NodeKind::Tuple, children))
+    }
+
+    fn seq(&mut self) -> Result<Node>
+    {
+        let mut children = Vec::new();
+        self.bump(&mut children);
+
+        self.items(&mut children, TokenKind::CloseBracket, ParseError::ExpectedArrayEnd, Parser::value)?;
+
+        Ok(Node::new(NodeKind::Seq, children))
+    }
+
+    /// Parses comma separated items up to and including `close`.
+    fn items<F>(
+        &mut self,
+        children: &mut Vec<Child>,
+        close: TokenKind,
+        end_error: ParseError,
+        mut item: F,
+    ) -> Result<()>
+        where F: FnMut(&mut Self) -> Result<Node>
+    {
+        loop {
+            self.trivia(children);
+
+            match self.peek_kind(0) {
+                Some(kind) if kind == close => break,
+                Some(_) => {}
+                None => return self.err(ParseError::Eof),
+            }
+
+            children.push(Child::Node(item(self)?));
+            self.trivia(children);
+
+            match self.peek_kind(0) {
+                Some(TokenKind::Comma) => self.bump(children),
+                Some(kind) if kind == close => break,
+                Some(_) => return self.err(end_error),
+                None => return self.err(ParseError::Eof),
+            }
+        }
+
+        self.bump(children);
+
+        Ok(())
+    }
+
+    fn field(&mut self) -> Result<Node>
+    {
+        if self.peek_kind(0) != Some(TokenKind::Ident) {
+            return self.err(ParseError::ExpectedIdentifier);
+        }
+
+        let mut children = Vec::new();
+        self.bump(&mut children);
+
+        self.entry_rest(children)
+    }
+
+    fn entry(&mut self) -> Result<Node>
+    {
+        let children = vec![Child::Node(self.value()?)];
+
+        self.entry_rest(children)
+    }
+
+    fn entry_rest(&mut self, mut children: Vec<Child>) -> Result<Node>
+    {
+        self.trivia(&mut children);
+
+        if self.peek_kind(0) != Some(TokenKind::Colon) {
+            return self.err(ParseError::ExpectedMapColon);
+        }
+
+        self.bump(&mut children);
+        self.trivia(&mut children);
+        children.push(Child::Node(self.value()?));
+
+        Ok(Node::new(NodeKind::Entry, children))
+    }
+}
@@ -0,0 +1,93 @@
+use super::*;
+
+const EXAMPLE: &str = include_str!("../../examples/example.rson");
+
+fn roundtrip(s: &str)
+{
+    assert_eq!(parse(s).expect("Failed to parse").to_string(), s);
+}
+
+fn err<T>(kind: ParseError, line: usize, col: usize) -> Result<T>
+{
+    Err(Error::Parser(kind, Position { line, col }))
+}
+
+#[test]
+fn test_roundtrip()
+{
+    roundtrip(EXAMPLE);
+    roundtrip("  42 // answer\n");
+    roundtrip("Some  (  () )");
+    roundtrip("r##\"raw \" string\"##");
+    roundtrip("[ 'a', '\\'', \"esc\\\"aped\", -1.5e3, ]");
+    roundtrip("/* a /* nested */ comment */ Game {\n\ttitle: \"RSON\",\n\tlevel: Level{},\n}\n");
+    roundtrip("{ (true, false,): 4, Custom(0.1, 0.8): [None,] }");
+    roundtrip("{ a: include!( /* a */ \"a.rson\" ), b: [include!(r\"b\")] }");
+    roundtrip("// a\nlet a = 1 ;\nlet /* b */ b=[a, a];\n\nb\n");
+}
+
+#[test]
+fn test_kinds()
+{
+    let doc = parse("Level { size: (10, 20), color: Yellow, tags: [], map: {\"a\": 1} }").unwrap();
+    let level = doc.value().unwrap();
+
+    assert_eq!(level.kind(), NodeKind::Struct);
+    assert_eq!(level.name(), Some("Level"));
+
+    let kinds: Vec<_> = level.items().map(|e| e.value().unwrap().kind()).collect();
+    assert_eq!(kinds, vec![NodeKind::Tuple, NodeKind::Literal, NodeKind::Seq, NodeKind::Map]);
+
+    let keys: Vec<_> = level.items().map(|e| e.key().unwrap().as_token().unwrap().text()).collect();
+    assert_eq!(keys, vec!["size", "color", "tags", "map"]);
+
+    let doc = parse("[include!(\"a\")]").unwrap();
+    let include = doc.value().unwrap().items().next().unwrap();
+
+    assert_eq!(include.kind(), NodeKind::Include);
+    assert_eq!(include.text(), "include!(\"a\")");
+
+    let doc = parse("let a = 1; let b = [a]; { b: b }").unwrap();
+    let bindings: Vec<_> = doc.bindings().map(|b| (b.name().unwrap(), b.value().unwrap().text())).collect();
+
+    assert_eq!(bindings, vec![("a", "1".to_owned()), ("b", "[a]".to_owned())]);
+    assert_eq!(doc.value().unwrap().kind(), NodeKind::Struct);
+    assert_eq!(parse("let").unwrap().value().unwrap().kind(), NodeKind::Literal);
+}
+
+#[test]
+fn test_trivia()
+{
+    let doc = parse("[\n    1, // one\n    /* two */ 2,\n]").unwrap();
+    let comments: Vec<_> = doc
+        .value()
+        .unwrap()
+        .tokens()
+        .filter(|t| t.kind().is_comment())
+        .map(Token::text)
+        .collect();
+
+    assert_eq!(comments, vec!["// one", "/* two */"]);
+}
+
+#[test]
+fn test_errors()
+{
+    assert_eq!(parse(""), err(ParseError::Eof, 1, 1));
+    assert_eq!(parse("{x: 1,\n y 2}"), err(ParseError::ExpectedMapColon, 2, 4));
+    assert_eq!(parse("[1 2]"), err(ParseError::ExpectedArrayEnd, 1, 4));
+    assert_eq!(parse("Name {\"a\": 1}"), err(ParseError::ExpectedIdentifier, 1, 7));
+    assert_eq!(parse("(1, 2) 3"), err(ParseError::TrailingCharacters, 1, 8));
+    assert_eq!(parse("\"open"), err(ParseError::Eof, 1, 1));
+    assert_eq!(parse("/* open /* */"), err(ParseError::Eof, 1, 1));
+    assert_eq!(parse("[1, @]"), err(ParseError::UnexpectedByte('@'), 1, 5));
+    assert_eq!(parse("\"\\q\""), err(ParseError::InvalidEscape, 1, 1));
+    assert_eq!(parse("[1, @]").unwrap_err().to_string(), "1:5: Unexpected byte");
+    assert_eq!(parse("include !(\"a\")"), err(ParseError::ExpectedInclude, 1, 9));
+    assert_eq!(parse("import!(\"a\")"), err(ParseError::ExpectedInclude, 1, 7));
+    assert_eq!(parse("include!(a)"), err(ParseError::ExpectedInclude, 1, 10));
+    assert_eq!(parse("let a = 1 a"), err(ParseError::ExpectedBinding, 1, 11));
+    assert_eq!(parse("let a 1; a"), err(ParseError::ExpectedBinding, 1, 7));
+    assert_eq!(parse("let a = 1;"), err(ParseError::Eof, 1, 11));
+    assert_eq!(parse("[1]; let a = 1;"), err(ParseError::TrailingCharacters, 1, 4));
+}
@@ -27,7 +27,8 @@ pub enum ParseError
     ExpectedArrayEnd,
     ExpectedBoolean,
     ExpectedComma,
-    ExpectedEnum,
+    /// Expected one of the listed variants.
+    ExpectedEnum(&'static [&'static str]),
     ExpectedChar,
     ExpectedFloat,
     ExpectedInteger,
@@ -43,13 +44,28 @@ pub enum ParseError
     ExpectedString,
     ExpectedStringEnd,
     ExpectedIdentifier,
+    ExpectedAttribute,
+    ExpectedAttributeEnd,
+    ExpectedInclude,
+    ExpectedBinding,
+    /// References to `let` bindings expand to too much data.
+    ExpansionLimit,
 
     InvalidEscape,
+    NoSuchExtension(String),
+    /// A field which is not one of the expected ones.
+    UnknownField(String, &'static [&'static str]),
+    /// A variant which is not one of the expected ones.
+    UnknownVariant(String, &'static [&'static str]),
 
     UnexpectedByte(char),
 
     Utf8Error(Utf8Error),
     TrailingCharacters,
+    /// An included file starts with `#![enable(..)]`.
+    IncludedAttribute,
+    /// An `include!` which has to be expanded with `include::expand` first.
+    UnexpandedInclude,
 
     #[doc(hidden)]
     __NonExhaustive,
@@ -62,17 +78,101 @@ impl fmt::Display for Error
         match *self {
             Error::IoError(ref s) => write!(f, "{}", s),
             Error::Message(ref s) => write!(f, "{}", s),
+            Error::Parser(ParseError::NoSuchExtension(ref name), pos) => {
+                write!(f, "{}: No such extension: `{}`", pos, name)
+            }
+            Error::Parser(ParseError::ExpectedEnum(variants), pos) => {
+                write!(f, "{}: Expected one of the variants ", pos)?;
+                write_list(f, variants)
+            }
+            Error::Parser(ParseError::UnknownField(ref found, expected), pos) => {
+                write!(f, "{}: Unknown field `{}`", pos, found)?;
+                write_suggestion(f, found, expected)
+            }
+            Error::Parser(ParseError::UnknownVariant(ref found, expected), pos) => {
+                write!(f, "{}: Unknown variant `{}`", pos, found)?;
+                write_suggestion(f, found, expected)
+            }
             Error::Parser(_, pos) => write!(f, "{}: {}", pos, self.description()),
         }
     }
 }
 
+fn write_list(f: &mut fmt::Formatter, items: &[&str]) -> fmt::Result
+{
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+
+        write!(f, "`{}`", item)?;
+    }
+
+    Ok(())
+}
+
+fn write_suggestion(f: &mut fmt::Formatter, found: &str, expected: &[&str]) -> fmt::Result
+{
+    if let Some(suggestion) = suggest(found, expected) {
+        write!(f, ", did you mean `{}`?", suggestion)
+    } else if expected.is_empty() {
+        f.write_str(", there are none")
+    } else {
+        f.write_str(", expected one of ")?;
+        write_list(f, expected)
+    }
+}
+
+/// Returns the expected name closest to `found`, if it's close enough
+/// to be a typo.
+fn suggest<'a>(found: &str, expected: &[&'a str]) -> Option<&'a str>
+{
+    expected
+        .iter()
+        .map(|e| (edit_distance(found, e), *e))
+        .filter(|&(distance, e)| distance <= ::std::cmp::max(found.len(), e.len()) / 3 + 1)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, e)| e)
+}
+
+/// The Levenshtein distance between `a` and `b`, counted in chars.
+fn edit_distance(a: &str, b: &str) -> usize
+{
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != cb) as usize;
+
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
 impl de::Error for Error
 {
     fn custom<T: fmt::Display>(msg: T) -> Self
     {
         Error::Message(msg.to_string())
     }
+
+    // The position is filled in by the deserializer.
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self
+    {
+        Error::Parser(ParseError::UnknownField(field.to_owned(), expected), Position { line: 0, col: 0 })
+    }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self
+    {
+        Error::Parser(ParseError::UnknownVariant(variant.to_owned(), expected), Position { line: 0, col: 0 })
+    }
 }
 
 impl StdError for Error
@@ -88,7 +188,7 @@ impl StdError for Error
                 ParseError::ExpectedArrayEnd => "Expected end of array",
                 ParseError::ExpectedBoolean => "Expected boolean",
                 ParseError::ExpectedComma => "Expected comma",
-                ParseError::ExpectedEnum => "Expected enum",
+                ParseError::ExpectedEnum(_) => "Expected enum",
                 ParseError::ExpectedChar => "Expected char",
                 ParseError::ExpectedFloat => "Expected float",
                 ParseError::ExpectedInteger => "Expected integer",
@@ -102,14 +202,27 @@ impl StdError for Error
                 ParseError::ExpectedUnit => "Expected unit",
                 ParseError::ExpectedStructName => "Expected struct name",
                 ParseError::ExpectedString => "Expected string",
+                ParseError::ExpectedStringEnd => "Expected end of string",
                 ParseError::ExpectedIdentifier => "Expected identifier",
+                ParseError::ExpectedAttribute => "Expected `#![enable(..)]` attribute",
+                ParseError::ExpectedAttributeEnd => "Expected end of attribute",
+                ParseError::ExpectedInclude => "Expected `include!(\"file\")`",
+                ParseError::ExpectedBinding => "Expected `let name = value;`",
+                ParseError::ExpansionLimit => "References expand to too much data",
 
                 ParseError::InvalidEscape => "Invalid escape sequence",
+                ParseError::NoSuchExtension(_) => "No such extension",
+                ParseError::UnknownField(..) => "Unknown field",
+                ParseError::UnknownVariant(..) => "Unknown variant",
+
+                ParseError::UnexpectedByte(_) => "Unexpected byte",
 
                 ParseError::Utf8Error(ref e) => e.description(),
                 ParseError::TrailingCharacters => "Non-whitespace trailing characters",
+                ParseError::IncludedAttribute => "Attributes are only allowed in the first file",
+                ParseError::UnexpandedInclude => "Includes have to be expanded first",
 
-                _ => unimplemented!(),
+                ParseError::__NonExhaustive => "Unknown error",
             }
         }
     }
@@ -1,6 +1,6 @@
 use serde::de::{self, Visitor};
 
-use super::{Deserializer, Error, Result};
+use super::{Deserializer, Error, ParseError, Result};
 
 pub struct IdDeserializer<'a, 'b: 'a>
 {
@@ -27,6 +27,10 @@ impl<'a, 'b: 'a, 'c> de::Deserializer<'b> for &'c mut IdDeserializer<'a, 'b>
     ) -> Result<V::Value>
         where V: Visitor<'b>
     {
+        if !self.d.options.json && self.d.bytes.peek() == Some(b'"') {
+            return self.d.bytes.err(ParseError::ExpectedIdentifier);
+        }
+
         self.d.deserialize_identifier(visitor)
     }
 
@@ -221,10 +225,10 @@ impl<'a, 'b: 'a, 'c> de::Deserializer<'b> for &'c mut IdDeserializer<'a, 'b>
 
     fn deserialize_ignored_any<V>(
         self,
-        _: V
+        visitor: V
     ) -> Result<V::Value>
         where V: Visitor<'b>
     {
-        unimplemented!("IdDeserializer may only be used for identifiers")
+        self.deserialize_identifier(visitor)
     }
 }
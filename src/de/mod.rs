@@ -2,51 +2,165 @@
 ///
 
 pub use self::error::{Error, ParseError, Result};
+pub use self::options::{Dialect, Options};
+pub use self::spanned::Spanned;
+pub use parse::Position;
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::io;
+use std::sync::atomic::{self, AtomicUsize};
+use std::sync::Arc;
 use std::str;
 
 use serde::de::{
     Deserialize, DeserializeOwned, DeserializeSeed,
-    EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor
+    EnumAccess, IgnoredAny, MapAccess, SeqAccess, VariantAccess, Visitor
 };
+use serde::de::value::{BorrowedStrDeserializer, SeqAccessDeserializer};
 use serde::Deserializer as SerdeDeserializer;
 
-use parse::Bytes;
+use parse::{Bytes, DIGITS, FLOAT_CHARS, IDENT_FIRST};
 use self::id::IdDeserializer;
+use self::spanned::SpannedAccess;
 
 mod error;
 mod id;
+mod options;
+mod spanned;
 #[cfg(test)]
 mod tests;
 mod value;
 
+/// The most bytes the references to `let` bindings in a document can
+/// expand to, as every binding can double the size of the one before.
+pub(crate) const MAX_EXPANSION: usize = 1 << 20;
+
 /// The RSON deserializer.
 ///
 /// If you just want to simply deserialize a value,
 /// you can use the `from_str` convenience function.
+///
+/// The input may start with `let name = value;` bindings, whose values
+/// are used wherever `name` is written later on. A binding can reference
+/// the bindings before it, and shadows variants and strings of the same
+/// name, but not map keys, struct names or variants with data.
+/// Documents whose references expand to more than 1 MiB are rejected.
+///
+/// Self-describing deserialization (`deserialize_any`), which serde uses
+/// for `Value`, untagged and internally tagged enums and flattened fields,
+/// drops struct names: `Name { x: 1 }` is read as the map `{ x: 1 }`.
+/// Internally tagged enums thus need the tag as a field, as in
+/// `{ type: Circle, r: 1.0 }`, and struct variants of an enum inside one
+/// of those types are written as `{ Circle: { r: 1.0 } }`.
+///
+/// ```
+/// use rson_rs::de::from_str;
+/// use rson_rs::value::Value;
+///
+/// let value: Value = from_str("
+///     let metal = { reflectivity: 1.0 };
+///
+///     { iron: metal, gold: metal }
+/// ").unwrap();
+///
+/// assert_eq!(value["gold"], from_str::<Value>("{ reflectivity: 1.0 }").unwrap());
+/// ```
 pub struct Deserializer<'de>
 {
-    bytes: Bytes<'de>
+    bytes: Bytes<'de>,
+    options: Options,
+    bindings: Arc<Vec<Binding<'de>>>,
+    /// How many of `bindings` can be referenced, or `None` before
+    /// the bindings at the start of the input were parsed.
+    visible: Option<usize>,
+    /// The number of bytes read again through references so far.
+    expanded: Arc<AtomicUsize>,
+    /// The lengths of tuple variants found while counting the elements of
+    /// an enclosing one, by the length of the input at their `(`.
+    tuple_lens: HashMap<usize, usize>,
+}
+
+/// A `let` binding, with the input starting at its value.
+#[derive(Clone)]
+struct Binding<'de>
+{
+    name: &'de [u8],
+    value: Bytes<'de>,
+    /// The length of the value.
+    len: usize,
 }
 
 impl<'de> Deserializer<'de>
 {
     pub fn from_str(input: &'de str) -> Self
     {
-        Deserializer {
-            bytes: Bytes::new(input.as_bytes()),
-        }
+        Deserializer::from_bytes(input.as_bytes())
     }
 
     pub fn from_bytes(input: &'de [u8]) -> Self
     {
         Deserializer {
             bytes: Bytes::new(input),
+            options: Options::default(),
+            bindings: Arc::new(Vec::new()),
+            visible: None,
+            expanded: Arc::new(AtomicUsize::new(0)),
+            tuple_lens: HashMap::new(),
         }
     }
 
+    /// Creates a deserializer reading `input` according to `options`.
+    ///
+    /// Fails if the input starts with invalid RON extension attributes.
+    pub fn with_options(input: &'de str, options: Options) -> Result<Self>
+    {
+        let mut deserializer = Deserializer {
+            options,
+            ..Deserializer::from_str(input)
+        };
+
+        if options.dialect == Dialect::Ron {
+            deserializer.extensions()?;
+        }
+
+        Ok(deserializer)
+    }
+
+    pub fn options(&self) -> &Options
+    {
+        &self.options
+    }
+
+    /// Returns the names of the `let` bindings at the start of the input,
+    /// with deserializers for their values.
+    ///
+    /// ```
+    /// extern crate rson_rs;
+    /// extern crate serde;
+    ///
+    /// use rson_rs::de::Deserializer;
+    /// use rson_rs::value::Value;
+    /// use serde::Deserialize;
+    ///
+    /// # fn main() {
+    /// let mut deserializer = Deserializer::from_str("let gold = 1.0; [gold, gold]");
+    /// let bindings = deserializer.bindings().unwrap();
+    /// let (name, mut gold) = bindings.into_iter().next().unwrap();
+    ///
+    /// assert_eq!(name, "gold");
+    /// assert_eq!(Value::deserialize(&mut gold).unwrap(), Value::from(1.0));
+    /// # }
+    /// ```
+    pub fn bindings(&mut self) -> Result<Vec<(&'de str, Deserializer<'de>)>>
+    {
+        self.let_bindings()?;
+
+        self.bindings.iter().enumerate()
+            .map(|(i, binding)| Ok((str::from_utf8(binding.name)?, self.fork(binding.value, i))))
+            .collect()
+    }
+
     pub fn remainder(&self) -> Cow<str>
     {
         String::from_utf8_lossy(&self.bytes.bytes())
@@ -66,6 +180,250 @@ impl<'de> Deserializer<'de>
     }
 }
 
+impl<'de> Deserializer<'de>
+{
+    /// Parses the `#![enable(..)]` attributes RON files may start with.
+    fn extensions(&mut self) -> Result<()>
+    {
+        while self.extension()? {
+            self.bytes.skip_ws();
+        }
+
+        Ok(())
+    }
+
+    /// Parses a single attribute, returning `false` if the input doesn't
+    /// start with one. Stops right after its closing `]`.
+    pub(crate) fn extension(&mut self) -> Result<bool>
+    {
+        if !self.bytes.consume("#") {
+            return Ok(false);
+        }
+
+        for token in &["!", "[", "enable", "("] {
+            self.bytes.skip_ws();
+
+            if !self.bytes.consume(token) {
+                return self.bytes.err(ParseError::ExpectedAttribute);
+            }
+        }
+
+        loop {
+            self.bytes.skip_ws();
+
+            if self.bytes.consume(")") {
+                break;
+            }
+
+            let start = self.bytes;
+            let name = str::from_utf8(self.bytes.identifier()?)?;
+
+            self.enable_extension(name).map_err(|kind| start.error(kind))?;
+
+            if !self.bytes.comma() {
+                if self.bytes.consume(")") {
+                    break;
+                }
+
+                return self.bytes.err(ParseError::ExpectedAttributeEnd);
+            }
+        }
+
+        self.bytes.skip_ws();
+
+        if !self.bytes.consume("]") {
+            return self.bytes.err(ParseError::ExpectedAttributeEnd);
+        }
+
+        Ok(true)
+    }
+
+    /// Parses the `let name = value;` bindings the input may start with,
+    /// unless they were already parsed.
+    fn let_bindings(&mut self) -> Result<()>
+    {
+        if self.visible.is_some() {
+            return Ok(());
+        }
+
+        self.visible = Some(0);
+
+        while self.check_binding() {
+            self.bytes.consume_ident("let");
+            self.bytes.skip_ws();
+
+            let name = self.bytes.identifier()?;
+            self.bytes.skip_ws();
+
+            if !self.bytes.consume("=") {
+                return self.bytes.err(ParseError::ExpectedBinding);
+            }
+
+            self.bytes.skip_ws();
+
+            let value = self.bytes;
+            self.bytes = {
+                let mut value = self.fork(value, self.bindings.len());
+                IgnoredAny::deserialize(&mut value)?;
+
+                value.bytes
+            };
+            let len = value.bytes().len() - self.bytes.bytes().len();
+            self.bytes.skip_ws();
+
+            if !self.bytes.consume(";") {
+                return self.bytes.err(ParseError::ExpectedBinding);
+            }
+
+            self.bytes.skip_ws();
+
+            Arc::make_mut(&mut self.bindings).push(Binding { name, value, len });
+            self.visible = Some(self.bindings.len());
+        }
+
+        Ok(())
+    }
+
+    /// Checks if a `let` binding follows.
+    fn check_binding(&self) -> bool
+    {
+        let mut bytes = self.bytes;
+
+        bytes.consume_ident("let") && { bytes.skip_ws(); bytes.identifier().is_ok() }
+    }
+
+    /// Returns a deserializer for the value of the binding referenced
+    /// next, consuming the reference, if there is one, along with the
+    /// position of the reference.
+    fn binding(&mut self) -> Result<Option<(Bytes<'de>, Deserializer<'de>)>>
+    {
+        self.let_bindings()?;
+
+        let visible = &self.bindings[..self.visible.unwrap_or(0)];
+        let mut bytes = self.bytes;
+
+        let name = match bytes.identifier() {
+            Ok(name) if !visible.is_empty() => name,
+            _ => return Ok(None),
+        };
+
+        let mut next = bytes;
+        next.skip_ws();
+
+        // Map keys, struct names and variants with data aren't references.
+        if let Some(b'{') | Some(b'(') | Some(b':') = next.peek() {
+            return Ok(None);
+        }
+
+        match visible.iter().rposition(|binding| binding.name == name) {
+            Some(i) => {
+                let len = visible[i].len;
+
+                if self.expanded.fetch_add(len, atomic::Ordering::Relaxed) + len > MAX_EXPANSION {
+                    return self.bytes.err(ParseError::ExpansionLimit);
+                }
+
+                let reference = self.bytes;
+                self.bytes = bytes;
+
+                Ok(Some((reference, self.fork(self.bindings[i].value, i))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Creates a deserializer reading from `bytes`, which can reference
+    /// the first `visible` bindings.
+    fn fork(&self, bytes: Bytes<'de>, visible: usize) -> Deserializer<'de>
+    {
+        Deserializer {
+            bytes,
+            options: self.options,
+            bindings: self.bindings.clone(),
+            visible: Some(visible),
+            expanded: self.expanded.clone(),
+            tuple_lens: HashMap::new(),
+        }
+    }
+
+    /// Turns on the RON extension called `name`.
+    fn enable_extension(&mut self, name: &str) -> ::std::result::Result<(), ParseError>
+    {
+        match name {
+            "implicit_some" => self.options.implicit_some = true,
+            "unwrap_newtypes" | "unwrap_variant_newtypes" => self.options.unwrap_newtypes = true,
+            _ => return Err(ParseError::NoSuchExtension(name.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    /// Visits integers as `u64` or `i64` if they fit and other
+    /// numbers as `f64`, so buffering deserializers keep the difference.
+    fn number<V>(&mut self, visitor: V) -> Result<V::Value>
+        where V: Visitor<'de>
+    {
+        let len = self.bytes.next_bytes_contained_in(FLOAT_CHARS);
+        let text = str::from_utf8(&self.bytes.bytes()[..len])?;
+
+        if text.bytes().skip(1).all(|b| DIGITS.contains(&b)) {
+            if let Ok(v) = text.parse() {
+                self.bytes.advance(len)?;
+
+                return visitor.visit_u64(v);
+            } else if let Ok(v) = text.parse() {
+                self.bytes.advance(len)?;
+
+                return visitor.visit_i64(v);
+            }
+        }
+
+        visitor.visit_f64(self.bytes.float()?)
+    }
+
+    /// Consumes a JSON `null` if JSON is accepted.
+    fn consume_null(&mut self) -> bool
+    {
+        self.options.json && self.bytes.consume_ident("null")
+    }
+
+    /// Checks if an explicit `Some(..)` follows.
+    fn check_some(&self) -> bool
+    {
+        let mut bytes = self.bytes;
+
+        bytes.consume_ident("Some") && { bytes.skip_ws(); bytes.peek() == Some(b'(') }
+    }
+
+    /// Checks if a newtype struct wrapped in `name(..)` follows.
+    fn check_newtype(&self, name: &str) -> bool
+    {
+        let mut bytes = self.bytes;
+
+        !name.is_empty() && bytes.consume_ident(name) && { bytes.skip_ws(); bytes.peek() == Some(b'(') }
+    }
+
+    /// Checks if a RON struct, `(field: ..)`, follows.
+    fn check_ron_struct(&self) -> bool
+    {
+        let mut bytes = self.bytes;
+
+        if self.options.dialect != Dialect::Ron || !bytes.consume("(") {
+            return false;
+        }
+
+        bytes.skip_ws();
+
+        if bytes.identifier().is_err() {
+            return false;
+        }
+
+        bytes.skip_ws();
+
+        bytes.consume(":")
+    }
+}
+
 /// A convenience function for reading data from a reader
 /// and feeding into a deserializer
 pub fn from_reader<R, T>(mut rdr: R) -> Result<T>
@@ -83,7 +441,14 @@ pub fn from_reader<R, T>(mut rdr: R) -> Result<T>
 pub fn from_str<'a, T>(s: &'a str) -> Result<T>
     where T: Deserialize<'a>
 {
-    let mut deserializer = Deserializer::from_str(s);
+    from_str_with_options(s, Options::default())
+}
+
+/// Like `from_str`, but reads `s` according to `options`.
+pub fn from_str_with_options<'a, T>(s: &'a str, options: Options) -> Result<T>
+    where T: Deserialize<'a>
+{
+    let mut deserializer = Deserializer::with_options(s, options)?;
     let t = T::deserialize(&mut deserializer)?;
 
     deserializer.end()?;
@@ -91,6 +456,20 @@ pub fn from_str<'a, T>(s: &'a str) -> Result<T>
     Ok(t)
 }
 
+/// Deserializes the value of a binding instead, if one is referenced.
+/// Errors are reported at the reference, as the value is valid RSON
+/// but may not fit the type expected there.
+macro_rules! binding {
+    ($de:ident, $method:ident($($arg:expr),*)) => {
+        if let Some((reference, mut value)) = $de.binding()? {
+            return (&mut value).$method($($arg),*).map_err(|e| match e {
+                Error::Parser(kind, _) => reference.error(kind),
+                e => e,
+            });
+        }
+    };
+}
+
 impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
 {
     type Error = Error;
@@ -98,29 +477,38 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_any(visitor));
+
         if self.bytes.consume_ident("true") {
             return visitor.visit_bool(true);
         } else if self.bytes.consume_ident("false") {
             return visitor.visit_bool(false);
         } else if self.bytes.check_ident("Some") {
             return self.deserialize_option(visitor);
-        } else if self.bytes.consume_ident("None") {
+        } else if self.bytes.consume_ident("None") || self.consume_null() {
             return visitor.visit_none();
         } else if self.bytes.consume("()") {
             return visitor.visit_unit();
         }
 
-        if self.bytes.identifier().is_ok() {
+        // Struct names are dropped, see the docs of `Deserializer`.
+        if let Ok(ident) = self.bytes.identifier() {
             self.bytes.skip_ws();
 
-            return self.deserialize_struct("", &[], visitor);
+            return match self.bytes.peek() {
+                Some(b'{') => self.deserialize_struct("", &[], visitor),
+                Some(b'(') if self.check_ron_struct() => self.deserialize_struct("", &[], visitor),
+                Some(b'(') => visitor.visit_map(VariantMap::new(ident, self)?),
+                _ => visitor.visit_borrowed_str(str::from_utf8(ident)?),
+            };
         }
 
         match self.bytes.peek_or_eof()? {
             b'{' => self.deserialize_map(visitor),
+            b'(' if self.check_ron_struct() => self.deserialize_struct("", &[], visitor),
             b'(' => self.deserialize_tuple(0, visitor),
             b'[' => self.deserialize_seq(visitor),
-            b'0' ..= b'9' | b'+' | b'-' | b'.' => self.deserialize_f64(visitor),
+            b'0' ..= b'9' | b'+' | b'-' | b'.' => self.number(visitor),
             b'"' => self.deserialize_string(visitor),
             b'\'' => self.deserialize_char(visitor),
             other => self.bytes.err(ParseError::UnexpectedByte(other as char)),
@@ -130,78 +518,104 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_bool(visitor));
+
         visitor.visit_bool(self.bytes.bool()?)
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_i8(visitor));
+
         visitor.visit_i8(self.bytes.signed_integer()?)
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_i16(visitor));
+
         visitor.visit_i8(self.bytes.signed_integer()?)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_i32(visitor));
+
         visitor.visit_i32(self.bytes.signed_integer()?)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_i64(visitor));
+
         visitor.visit_i64(self.bytes.signed_integer()?)
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_u8(visitor));
+
         visitor.visit_u8(self.bytes.unsigned_integer()?)
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_u16(visitor));
+
         visitor.visit_u16(self.bytes.unsigned_integer()?)
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_u32(visitor));
+
         visitor.visit_u32(self.bytes.unsigned_integer()?)
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_u64(visitor));
+
         visitor.visit_u64(self.bytes.unsigned_integer()?)
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_f32(visitor));
+
         visitor.visit_f32(self.bytes.float()?)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_f64(visitor));
+
         visitor.visit_f64(self.bytes.float()?)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_char(visitor));
+
         visitor.visit_char(self.bytes.char()?)
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_str(visitor));
+
         use parse::ParsedStr;
 
         match self.bytes.string()? {
@@ -231,6 +645,16 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_option(visitor));
+
+        if self.options.implicit_some && !self.check_some() {
+            if self.bytes.consume_ident("None") || self.consume_null() {
+                return visitor.visit_none();
+            }
+
+            return visitor.visit_some(&mut *self);
+        }
+
         if self.bytes.consume("Some") && { self.bytes.skip_ws(); self.bytes.consume("(") } {
             self.bytes.skip_ws();
 
@@ -244,7 +668,7 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
                 self.bytes.err(ParseError::ExpectedOptionEnd)
             }
 
-        } else if self.bytes.consume("None") {
+        } else if self.bytes.consume("None") || self.consume_null() {
             visitor.visit_none()
         } else {
             self.bytes.err(ParseError::ExpectedOption)
@@ -255,7 +679,9 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
-        if self.bytes.consume("()") {
+        binding!(self, deserialize_unit(visitor));
+
+        if self.bytes.consume("()") || self.consume_null() {
             visitor.visit_unit()
         } else {
             self.bytes.err(ParseError::ExpectedUnit)
@@ -269,6 +695,8 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     ) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_unit_struct(name, visitor));
+
         if self.bytes.consume(name) {
             visitor.visit_unit()
         } else {
@@ -283,6 +711,12 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     ) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_newtype_struct(name, visitor));
+
+        if self.options.unwrap_newtypes && !self.check_newtype(name) {
+            return visitor.visit_newtype_struct(&mut *self);
+        }
+
         self.bytes.consume(name);
 
         self.bytes.skip_ws();
@@ -304,6 +738,8 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_seq(visitor));
+
         if self.bytes.consume("[") {
             let value = visitor.visit_seq(CommaSeparated::new(b']', &mut self, 0))?;
             self.bytes.comma();
@@ -326,22 +762,28 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     // tuple before even looking at the input data.
     fn deserialize_tuple<V>(
         mut self,
-        _len: usize,
+        len: usize,
         visitor: V
     ) -> Result<V::Value>
         where V: Visitor<'de>
     {
-        if self.bytes.consume("(") {
-            let value = visitor.visit_seq(CommaSeparated::new(b')', &mut self, 0))?;
-            self.bytes.comma();
+        binding!(self, deserialize_tuple(len, visitor));
 
-            if self.bytes.consume(")") {
-                Ok(value)
-            } else {
-                self.bytes.err(ParseError::ExpectedArrayEnd)
-            }
+        let end = if self.bytes.consume("(") {
+            ")"
+        } else if self.options.json && self.bytes.consume("[") {
+            "]"
         } else {
-            self.bytes.err(ParseError::ExpectedArray)
+            return self.bytes.err(ParseError::ExpectedArray);
+        };
+
+        let value = visitor.visit_seq(CommaSeparated::new(end.as_bytes()[0], &mut self, 0))?;
+        self.bytes.comma();
+
+        if self.bytes.consume(end) {
+            Ok(value)
+        } else {
+            self.bytes.err(ParseError::ExpectedArrayEnd)
         }
     }
 
@@ -353,6 +795,8 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     ) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_tuple_struct(name, len, visitor));
+
         self.bytes.consume(name);
         self.deserialize_tuple(len, visitor)
     }
@@ -360,6 +804,16 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     fn deserialize_map<V>(mut self, visitor: V) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        binding!(self, deserialize_map(visitor));
+
+        // Serde reads structs with flattened fields as maps,
+        // so skip the name of a struct.
+        let mut bytes = self.bytes;
+
+        if bytes.identifier().is_ok() && { bytes.skip_ws(); bytes.peek() == Some(b'{') } {
+            self.bytes = bytes;
+        }
+
         if self.bytes.consume("{") {
             let value = visitor.visit_map(CommaSeparated::new(b'}', &mut self, 0))?;
             self.bytes.comma();
@@ -377,38 +831,50 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     fn deserialize_struct<V>(
         mut self,
         name: &'static str,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V
     ) -> Result<V::Value>
         where V: Visitor<'de>
     {
+        if name == spanned::NAME && fields == spanned::FIELDS {
+            return visitor.visit_map(SpannedAccess::new(self));
+        }
+
+        binding!(self, deserialize_struct(name, fields, visitor));
+
         self.bytes.consume(name);
 
         self.bytes.skip_ws();
 
-        if self.bytes.consume("{") {
-            let value = visitor.visit_map(CommaSeparated::new(b'}', &mut self, Flags::IS_STRUCT))?;
-            self.bytes.comma();
+        let end = if self.bytes.consume("{") {
+            "}"
+        } else if self.options.dialect == Dialect::Ron && self.bytes.consume("(") {
+            ")"
+        } else {
+            return self.bytes.err(ParseError::ExpectedStruct);
+        };
 
-            if self.bytes.consume("}") {
-                Ok(value)
-            } else {
-                self.bytes.err(ParseError::ExpectedStructEnd)
-            }
+        let value = visitor.visit_map(CommaSeparated::new(end.as_bytes()[0], &mut self, Flags::IS_STRUCT))?;
+        self.bytes.comma();
+
+        if self.bytes.consume(end) {
+            Ok(value)
         } else {
-            self.bytes.err(ParseError::ExpectedStruct)
+            self.bytes.err(ParseError::ExpectedStructEnd)
         }
     }
 
     fn deserialize_enum<V>(
         self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
+        name: &'static str,
+        variants: &'static [&'static str],
         visitor: V
     ) -> Result<V::Value>
         where V: Visitor<'de>
     {
-        visitor.visit_enum(Enum::new(self))
+        binding!(self, deserialize_enum(name, variants, visitor));
+
+        visitor.visit_enum(Enum::new(self, name, variants))
     }
 
     fn deserialize_identifier<V>(
@@ -417,7 +883,13 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     ) -> Result<V::Value>
         where V: Visitor<'de>
     {
-        visitor.visit_bytes(self.bytes.identifier()?)
+        // Variant names may be quoted, as in `{ type: "Circle" }` for
+        // an internally tagged enum.
+        if self.bytes.peek() == Some(b'"') {
+            return self.deserialize_str(visitor);
+        }
+
+        visitor.visit_borrowed_str(str::from_utf8(self.bytes.identifier()?)?)
     }
 
     fn deserialize_ignored_any<V>(
@@ -430,6 +902,16 @@ impl<'de, 'a> SerdeDeserializer<'de> for &'a mut Deserializer<'de>
     }
 }
 
+/// Gives errors created by serde, like unknown fields, which don't
+/// have a position yet the position of `bytes`.
+fn locate(error: Error, bytes: &Bytes) -> Error
+{
+    match error {
+        Error::Parser(kind, Position { line: 0, .. }) => bytes.error(kind),
+        error => error,
+    }
+}
+
 type Flags = u8;
 
 trait CommaSeparatedFlag
@@ -523,11 +1005,14 @@ impl<'de, 'a> MapAccess<'de> for CommaSeparated<'a, 'de>
                     self.flags |= Flags::IS_MAP;
                 }
             }
-            if self.flags.is_struct() {
-                seed.deserialize(&mut IdDeserializer::new(&mut *self.de)).map(Some)
+            let start = self.de.bytes;
+            let key = if self.flags.is_struct() {
+                seed.deserialize(&mut IdDeserializer::new(&mut *self.de))
             } else {
-                seed.deserialize(&mut *self.de).map(Some)
-            }
+                seed.deserialize(&mut *self.de)
+            };
+
+            key.map(Some).map_err(|e| locate(e, &start))
         } else {
             Ok(None)
         }
@@ -541,7 +1026,8 @@ impl<'de, 'a> MapAccess<'de> for CommaSeparated<'a, 'de>
         if self.de.bytes.consume(":") {
             self.de.bytes.skip_ws();
 
-            let res = seed.deserialize(&mut *self.de)?;
+            let start = self.de.bytes;
+            let res = seed.deserialize(&mut *self.de).map_err(|e| locate(e, &start))?;
 
             self.flags |= if self.de.bytes.comma() { Flags::HAD_COMMA } else { 0 };
 
@@ -552,16 +1038,175 @@ impl<'de, 'a> MapAccess<'de> for CommaSeparated<'a, 'de>
     }
 }
 
+/// A tuple variant seen by `deserialize_any`, which is visited as
+/// a map from the variant name to its single value or to a sequence.
+struct VariantMap<'a, 'de: 'a>
+{
+    de: &'a mut Deserializer<'de>,
+    variant: Option<&'de str>,
+}
+
+impl<'a, 'de> VariantMap<'a, 'de>
+{
+    fn new(variant: &'de [u8], de: &'a mut Deserializer<'de>) -> Result<Self>
+    {
+        Ok(VariantMap {
+            de,
+            variant: Some(str::from_utf8(variant)?),
+        })
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for VariantMap<'a, 'de>
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+        where K: DeserializeSeed<'de>
+    {
+        match self.variant.take() {
+            Some(variant) => seed.deserialize(BorrowedStrDeserializer::new(variant)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+        where V: DeserializeSeed<'de>
+    {
+        let key = self.de.bytes.bytes().len();
+        let len = match self.de.tuple_lens.remove(&key) {
+            Some(len) => len,
+            None => tuple_len(self.de.bytes, &mut self.de.tuple_lens),
+        };
+
+        self.de.bytes.consume("(");
+        self.de.bytes.skip_ws();
+
+        let value = if len == 1 {
+            seed.deserialize(&mut *self.de)?
+        } else {
+            seed.deserialize(SeqAccessDeserializer::new(CommaSeparated::new(b')', self.de, 0)))?
+        };
+        self.de.bytes.comma();
+
+        if self.de.bytes.consume(")") {
+            Ok(value)
+        } else {
+            self.de.bytes.err(ParseError::ExpectedArrayEnd)
+        }
+    }
+}
+
+/// Counts the elements of the tuple `bytes` starts with, up to two,
+/// by looking for a comma outside of nested brackets. The tuples in its
+/// first element are counted on the way and added to `lens`, so nested
+/// tuple variants are only scanned once.
+///
+/// Invalid input counts as one element, so the error
+/// is reported when the element is read.
+fn tuple_len(mut bytes: Bytes, lens: &mut HashMap<usize, usize>) -> usize
+{
+    // The open brackets, with the input length and the element count
+    // of the tuples among them.
+    let mut open: Vec<Option<(usize, usize)>> = Vec::new();
+
+    loop {
+        bytes.skip_ws();
+
+        let skipped = match bytes.peek() {
+            None => return 1,
+            Some(b'(') => {
+                let key = bytes.bytes().len();
+                let _ = bytes.advance_single();
+                bytes.skip_ws();
+
+                open.push(Some((key, if bytes.peek() == Some(b')') { 0 } else { 1 })));
+
+                Ok(())
+            }
+            Some(b'[') | Some(b'{') => {
+                open.push(None);
+                bytes.advance_single()
+            }
+            Some(b')') | Some(b']') | Some(b'}') => {
+                match open.pop() {
+                    Some(Some((_, len))) if open.is_empty() => return len,
+                    Some(Some((key, len))) => {
+                        lens.insert(key, len);
+                    }
+                    Some(None) => {}
+                    None => return 1,
+                }
+
+                bytes.advance_single()
+            }
+            Some(b',') => {
+                bytes.comma();
+
+                if let Some(&mut Some((_, ref mut len))) = open.last_mut() {
+                    if bytes.peek() != Some(b')') {
+                        *len = 2;
+                    }
+                }
+
+                if open.len() == 1 && open[0].map(|(_, len)| len) == Some(2) {
+                    return 2;
+                }
+
+                Ok(())
+            }
+            Some(b'"') => bytes.string().map(|_| ()),
+            Some(b'r') if matches!(bytes.bytes().get(1), Some(b'"') | Some(b'#')) => bytes.string().map(|_| ()),
+            Some(b'\'') => bytes.char().map(|_| ()),
+            Some(b) if IDENT_FIRST.contains(&b) => bytes.identifier().map(|_| ()),
+            Some(_) => bytes.advance_single(),
+        };
+
+        if skipped.is_err() {
+            return 1;
+        }
+    }
+}
+
 struct Enum<'a, 'de: 'a>
 {
     de: &'a mut Deserializer<'de>,
+    name: &'static str,
+    variants: &'static [&'static str],
 }
 
 impl<'a, 'de> Enum<'a, 'de>
 {
-    fn new(de: &'a mut Deserializer<'de>) -> Self
+    fn new(de: &'a mut Deserializer<'de>, name: &'static str, variants: &'static [&'static str]) -> Self
+    {
+        Enum { de, name, variants }
+    }
+
+    /// Skips the `Name::` of a path-qualified variant and checks that
+    /// a variant name follows. Unknown names are left to the visitor,
+    /// so `#[serde(other)]` works.
+    fn check_variant(&mut self) -> Result<()>
     {
-        Enum { de }
+        self.de.bytes.skip_ws();
+
+        let mut path = self.de.bytes;
+
+        if !self.name.is_empty() && path.consume_ident(self.name) && { path.skip_ws(); path.consume("::") } {
+            path.skip_ws();
+            self.de.bytes = path;
+        }
+
+        let mut bytes = self.de.bytes;
+        let is_name = match bytes.peek() {
+            Some(b'"') => bytes.string().is_ok(),
+            _ => bytes.identifier().is_ok(),
+        };
+
+        if is_name {
+            Ok(())
+        } else {
+            self.de.bytes.err(ParseError::ExpectedEnum(self.variants))
+        }
     }
 }
 
@@ -570,10 +1215,13 @@ impl<'de, 'a> EnumAccess<'de> for Enum<'a, 'de>
     type Error = Error;
     type Variant = Self;
 
-    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    fn variant_seed<V>(mut self, seed: V) -> Result<(V::Value, Self::Variant)>
         where V: DeserializeSeed<'de>
     {
-        let value = seed.deserialize(&mut *self.de)?;
+        self.check_variant()?;
+
+        let start = self.de.bytes;
+        let value = seed.deserialize(&mut *self.de).map_err(|e| locate(e, &start))?;
 
         Ok((value, self))
     }
@@ -585,14 +1233,23 @@ impl<'de, 'a> VariantAccess<'de> for Enum<'a, 'de>
 
     fn unit_variant(self) -> Result<()>
     {
-        Ok(())
+        self.de.bytes.skip_ws();
+
+        match self.de.bytes.peek() {
+            Some(b @ b'(') | Some(b @ b'{') => self.de.bytes.err(ParseError::UnexpectedByte(b as char)),
+            _ => Ok(()),
+        }
     }
 
     fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
         where T: DeserializeSeed<'de>
     {
         self.de.bytes.skip_ws();
-        
+
+        if self.de.options.unwrap_newtypes && (self.de.bytes.peek() == Some(b'{') || self.de.check_ron_struct()) {
+            return seed.deserialize(&mut *self.de);
+        }
+
         if self.de.bytes.consume("(") {
             let val = seed.deserialize(&mut *self.de)?;
 
@@ -0,0 +1,52 @@
+/// The syntax accepted by the deserializer.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Dialect
+{
+    /// Plain RSON.
+    #[default]
+    Rson,
+    /// RSON plus the syntax of RON, which RSON was forked from:
+    /// structs written as `Name(field: value)` and `#![enable(..)]`
+    /// extension attributes at the start of the input.
+    Ron,
+}
+
+/// Options controlling what the deserializer accepts.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Options
+{
+    pub dialect: Dialect,
+    /// Also accept JSON: quoted field names and `null`, which is read as
+    /// `None` or unit. Tuples can be written as `[..]`.
+    pub json: bool,
+    /// Accept a bare value as `Some(value)` for options. `Some(..)` can
+    /// still be written, e.g. for `Some(None)`. This is RON's
+    /// `implicit_some` extension.
+    pub implicit_some: bool,
+    /// Read newtype structs as their content, e.g. `3` for `Meters(3)`,
+    /// and newtype variants holding a struct as `Variant { .. }`. The
+    /// wrapped forms are still accepted. This covers RON's
+    /// `unwrap_newtypes` and `unwrap_variant_newtypes` extensions.
+    pub unwrap_newtypes: bool,
+}
+
+impl Options
+{
+    /// Options for reading RON files.
+    pub fn ron() -> Self
+    {
+        Options {
+            dialect: Dialect::Ron,
+            ..Options::default()
+        }
+    }
+
+    /// Options for reading JSON files as well as RSON ones.
+    pub fn json() -> Self
+    {
+        Options {
+            json: true,
+            ..Options::default()
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use serde::de::{self, Deserialize, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::de::value::BorrowedStrDeserializer;
+use serde::ser::{Serialize, Serializer};
+
+use parse::Position;
+use super::{Deserializer, Error, Result};
+
+pub const NAME: &str = "$__rson_private_Spanned";
+pub const FIELDS: &[&str] = &[
+    "$__rson_private_start_line",
+    "$__rson_private_start_col",
+    "$__rson_private_value",
+    "$__rson_private_end_line",
+    "$__rson_private_end_col",
+];
+
+/// A value together with the positions where it starts and ends in
+/// the input.
+///
+/// The positions are only known when deserializing with `Deserializer`
+/// directly; buffering types like untagged enums lose them. Comparing
+/// and hashing only looks at the value.
+///
+/// ```
+/// use rson_rs::de::{from_str, Position, Spanned};
+///
+/// let textures: Vec<Spanned<String>> = from_str("[\n    \"grass.png\",\n    \"stone.png\",\n]").unwrap();
+///
+/// assert_eq!(textures[1].get_ref(), "stone.png");
+/// assert_eq!(textures[1].start(), Position { line: 3, col: 5 });
+/// assert_eq!(textures[1].end(), Position { line: 3, col: 16 });
+/// ```
+#[derive(Clone, Debug)]
+pub struct Spanned<T>
+{
+    start: Position,
+    end: Position,
+    value: T,
+}
+
+impl<T> Spanned<T>
+{
+    /// The position of the first byte of the value.
+    pub fn start(&self) -> Position
+    {
+        self.start
+    }
+
+    /// The position right after the last byte of the value.
+    pub fn end(&self) -> Position
+    {
+        self.end
+    }
+
+    pub fn get_ref(&self) -> &T
+    {
+        &self.value
+    }
+
+    pub fn get_mut(&mut self) -> &mut T
+    {
+        &mut self.value
+    }
+
+    pub fn into_inner(self) -> T
+    {
+        self.value
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T>
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq> Eq for Spanned<T> {}
+
+impl<T: Hash> Hash for Spanned<T>
+{
+    fn hash<H: Hasher>(&self, state: &mut H)
+    {
+        self.value.hash(state)
+    }
+}
+
+impl<T: Serialize> Serialize for Spanned<T>
+{
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        self.value.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Spanned<T>
+    where T: Deserialize<'de>
+{
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        deserializer.deserialize_struct(NAME, FIELDS, SpannedVisitor(Default::default()))
+    }
+}
+
+struct SpannedVisitor<T>(::std::marker::PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for SpannedVisitor<T>
+    where T: Deserialize<'de>
+{
+    type Value = Spanned<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.write_str("a spanned value")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> ::std::result::Result<Self::Value, A::Error>
+        where A: MapAccess<'de>
+    {
+        let start = Position { line: entry(&mut map, FIELDS[0])?, col: entry(&mut map, FIELDS[1])? };
+        let value = entry(&mut map, FIELDS[2])?;
+        let end = Position { line: entry(&mut map, FIELDS[3])?, col: entry(&mut map, FIELDS[4])? };
+
+        Ok(Spanned { start, end, value })
+    }
+}
+
+fn entry<'de, A, V>(map: &mut A, name: &'static str) -> ::std::result::Result<V, A::Error>
+    where A: MapAccess<'de>,
+          V: Deserialize<'de>
+{
+    match map.next_key::<&str>()? {
+        Some(key) if key == name => map.next_value(),
+        _ => Err(de::Error::missing_field(name)),
+    }
+}
+
+/// Presents a value and its positions as the fields of a `Spanned`.
+pub struct SpannedAccess<'a, 'de: 'a>
+{
+    de: &'a mut Deserializer<'de>,
+    start: Position,
+    end: Position,
+    index: usize,
+}
+
+impl<'a, 'de> SpannedAccess<'a, 'de>
+{
+    pub fn new(de: &'a mut Deserializer<'de>) -> Self
+    {
+        de.bytes.skip_ws();
+
+        let start = de.bytes.position();
+
+        SpannedAccess { de, start, end: start, index: 0 }
+    }
+}
+
+impl<'a, 'de> MapAccess<'de> for SpannedAccess<'a, 'de>
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+        where K: DeserializeSeed<'de>
+    {
+        match FIELDS.get(self.index) {
+            Some(field) => seed.deserialize(BorrowedStrDeserializer::new(field)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+        where V: DeserializeSeed<'de>
+    {
+        self.index += 1;
+
+        match self.index {
+            1 => seed.deserialize(self.start.line.into_deserializer()),
+            2 => seed.deserialize(self.start.col.into_deserializer()),
+            3 => {
+                let value = seed.deserialize(&mut *self.de)?;
+                self.end = self.de.bytes.position();
+
+                Ok(value)
+            }
+            4 => seed.deserialize(self.end.line.into_deserializer()),
+            _ => seed.deserialize(self.end.col.into_deserializer()),
+        }
+    }
+}
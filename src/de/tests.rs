@@ -62,6 +62,57 @@ fn test_enum()
     assert_eq!(Ok(MyEnum::B(true)), from_str("B(true,)"));
     assert_eq!(Ok(MyEnum::C(true, 3.5)), from_str("C(true,3.5,)"));
     assert_eq!(Ok(MyEnum::D { a: 2, b: 3 }), from_str("D{a:2,b:3,}"));
+
+    assert_eq!(Ok(MyEnum::A), from_str("MyEnum::A"));
+    assert_eq!(Ok(MyEnum::B(false)), from_str("MyEnum :: B(false)"));
+    assert_eq!(Ok(vec![MyEnum::A]), from_str("[MyEnum::A]"));
+
+    // Variants named like their enum.
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Kind
+    {
+        Kind,
+        Other,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Value
+    {
+        Value(u8),
+    }
+
+    assert_eq!(Ok(Kind::Kind), from_str("Kind"));
+    assert_eq!(Ok(Kind::Kind), from_str("Kind::Kind"));
+    assert_eq!(Ok(Kind::Other), from_str("Kind::Other"));
+    assert_eq!(Ok(Value::Value(3)), from_str("Value(3)"));
+    assert_eq!(Ok(Value::Value(3)), from_str("Value::Value(3)"));
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Fallback
+    {
+        Known,
+        #[serde(other)]
+        Other,
+    }
+
+    assert_eq!(Ok(Fallback::Known), from_str("Known"));
+    assert_eq!(Ok(Fallback::Other), from_str("Zzz"));
+    assert_eq!(Ok(vec![Fallback::Other]), from_str("[Fallback::Zzz]"));
+}
+
+#[test]
+fn test_enum_error()
+{
+    const VARIANTS: &[&str] = &["A", "B", "C", "D"];
+
+    assert_eq!(from_str::<MyEnum>("E"), err(ParseError::UnknownVariant("E".to_owned(), VARIANTS), 1, 1));
+    assert_eq!(from_str::<MyEnum>("Other::A"), err(ParseError::UnknownVariant("Other".to_owned(), VARIANTS), 1, 1));
+    assert_eq!(from_str::<MyEnum>("MyEnum::E"), err(ParseError::UnknownVariant("E".to_owned(), VARIANTS), 1, 9));
+    assert_eq!(from_str::<MyEnum>("A(1)"), err(ParseError::UnexpectedByte('('), 1, 2));
+    assert_eq!(
+        from_str::<MyEnum>("1").unwrap_err().to_string(),
+        "1:1: Expected one of the variants `A`, `B`, `C`, `D`",
+    );
 }
 
 #[test]
@@ -110,6 +161,20 @@ fn test_raw_string()
 fn test_char()
 {
     assert_eq!(Ok('c'), from_str("'c'"));
+    assert_eq!(Ok(vec!['é', '日', '🦀']), from_str("['é', '日', '🦀']"));
+}
+
+#[test]
+fn test_char_utf8()
+{
+    let parse = |bytes| char::deserialize(&mut Deserializer::from_bytes(bytes));
+
+    assert_eq!(parse(b"'\xC3\xA9'"), Ok('é'));
+    assert_eq!(parse(b"'\xE6\x97\xA5'"), Ok('日'));
+    assert_eq!(parse(b"'\xF0\x9F\xA6\x80'"), Ok('🦀'));
+    assert_eq!(parse(b"'\xE6\x97'"), err(ParseError::ExpectedChar, 1, 2));
+    assert_eq!(parse(b"'\xF0\x9F\xA6"), err(ParseError::ExpectedChar, 1, 2));
+    assert_eq!(parse(b"'\xA9'"), err(ParseError::ExpectedChar, 1, 2));
 }
 
 #[test]
@@ -156,7 +221,7 @@ fn test_err_wrong_value()
     assert_eq!(from_str::<HashMap<u32, u32>>("'c'"), err(ExpectedMap, 1, 1));
     assert_eq!(from_str::<[u8; 5]>("'c'"), err(ExpectedArray, 1, 1));
     assert_eq!(from_str::<Vec<u32>>("'c'"), err(ExpectedArray, 1, 1));
-    assert_eq!(from_str::<MyEnum>("'c'"), err(ExpectedIdentifier, 1, 1));
+    assert_eq!(from_str::<MyEnum>("'c'"), err(ExpectedEnum(&["A", "B", "C", "D"]), 1, 1));
     assert_eq!(from_str::<MyStruct>("'c'"), err(ExpectedStruct, 1, 1));
     assert_eq!(from_str::<(u8, bool)>("'c'"), err(ExpectedArray, 1, 1));
     assert_eq!(from_str::<bool>("notabool"), err(ExpectedBoolean, 1, 1));
@@ -186,3 +251,395 @@ fn untagged()
 
     assert_eq!(from_str::<Untagged>("true").unwrap(), Untagged::Bool(true));
 }
+
+#[test]
+fn test_ron_structs()
+{
+    let ron = Options::ron();
+
+    assert_eq!(from_str_with_options("MyStruct(x: 1, y: 2,)", ron), Ok(MyStruct { x: 1.0, y: 2.0 }));
+    assert_eq!(from_str_with_options("( x: 1, y: 2 )", ron), Ok(MyStruct { x: 1.0, y: 2.0 }));
+    assert_eq!(from_str_with_options("MyStruct { x: 1, y: 2 }", ron), Ok(MyStruct { x: 1.0, y: 2.0 }));
+    assert_eq!(from_str_with_options("D(a: 2, b: 3)", ron), Ok(MyEnum::D { a: 2, b: 3 }));
+    assert_eq!(from_str_with_options("EmptyStruct2()", ron), Ok(EmptyStruct2 {}));
+    assert_eq!(from_str_with_options("C(true, 3.5)", ron), Ok(MyEnum::C(true, 3.5)));
+
+    assert_eq!(from_str::<MyStruct>("(x: 1, y: 2)"), err(ParseError::ExpectedStruct, 1, 1));
+}
+
+#[test]
+fn test_ron_any()
+{
+    use value::Value;
+
+    let value: Value = from_str_with_options("Scene(size: (1, 2), owner: Some(Owner(name: \"a\")))", Options::ron()).unwrap();
+
+    assert_eq!(value, from_str("{ size: (1, 2), owner: Some({ name: \"a\" }) }").unwrap());
+}
+
+#[test]
+fn test_ron_extensions()
+{
+    let ron = Options::ron();
+
+    assert_eq!(from_str_with_options("#![enable()] #![enable()]\n(x: 1, y: 2)", ron), Ok(MyStruct { x: 1.0, y: 2.0 }));
+    assert_eq!(
+        from_str_with_options::<u8>("#![enable(unknown)] 1", ron),
+        err(ParseError::NoSuchExtension("unknown".to_owned()), 1, 11),
+    );
+    assert_eq!(from_str_with_options::<u8>("#![enable 1", ron), err(ParseError::ExpectedAttribute, 1, 11));
+    assert_eq!(from_str_with_options::<u8>("#![enable()", ron), err(ParseError::ExpectedAttributeEnd, 1, 12));
+    assert!(from_str::<u8>("#![enable()] 1").is_err());
+}
+
+#[test]
+fn test_json()
+{
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config
+    {
+        name: String,
+        origin: MyStruct,
+        size: (u8, u8),
+        parent: Option<u8>,
+        kind: MyEnum,
+        unit: (),
+    }
+
+    let json = r#"{
+        "name": "a\/b", "origin": {"x": 1, "y": 2.5}, "size": [3, 4],
+        "parent": null, "kind": "A", "unit": null
+    }"#;
+    let config = Config {
+        name: "a/b".to_owned(),
+        origin: MyStruct { x: 1.0, y: 2.5 },
+        size: (3, 4),
+        parent: None,
+        kind: MyEnum::A,
+        unit: (),
+    };
+
+    assert_eq!(from_str_with_options(json, Options::json()), Ok(config));
+    assert_eq!(from_str_with_options("{x: 1, \"y\": 2}", Options::json()), Ok(MyStruct { x: 1.0, y: 2.0 }));
+    assert!(from_str::<MyStruct>("{\"x\": 1, \"y\": 2}").is_err());
+    assert!(from_str::<Option<u8>>("null").is_err());
+}
+
+#[test]
+fn test_implicit_some()
+{
+    let options = Options { implicit_some: true, ..Options::default() };
+
+    assert_eq!(from_str_with_options("1", options), Ok(Some(1u8)));
+    assert_eq!(from_str_with_options("Some(1)", options), Ok(Some(1u8)));
+    assert_eq!(from_str_with_options("None", options), Ok(None::<u8>));
+    assert_eq!(from_str_with_options("1", options), Ok(Some(Some(1u8))));
+    assert_eq!(from_str_with_options("Some(None)", options), Ok(Some(None::<u8>)));
+    assert_eq!(from_str_with_options("None", options), Ok(None::<Option<u8>>));
+    assert_eq!(from_str_with_options("[A, None]", options), Ok(vec![Some(MyEnum::A), None]));
+    assert_eq!(
+        from_str_with_options("#![enable(implicit_some)] (x: 1, y: 2)", Options::ron()),
+        Ok(Some(MyStruct { x: 1.0, y: 2.0 })),
+    );
+    assert!(from_str::<Option<u8>>("1").is_err());
+}
+
+#[test]
+fn test_unwrap_newtypes()
+{
+    #[derive(Clone, Debug, PartialEq, Deserialize)]
+    struct Meters(f32);
+
+    #[derive(Clone, Debug, PartialEq, Deserialize)]
+    enum Shape
+    {
+        Line(Meters),
+        Rect(MyStruct),
+    }
+
+    let options = Options { unwrap_newtypes: true, ..Options::default() };
+    let shapes = vec![Shape::Line(Meters(3.0)), Shape::Rect(MyStruct { x: 1.0, y: 2.0 })];
+
+    assert_eq!(from_str_with_options("[Line(3), Rect { x: 1, y: 2 }]", options), Ok(shapes.clone()));
+    assert_eq!(from_str_with_options("Meters(3)", options), Ok(Meters(3.0)));
+    assert_eq!(
+        from_str_with_options("#![enable(unwrap_variant_newtypes)] [Line(3), Rect(x: 1, y: 2)]", Options::ron()),
+        Ok(shapes),
+    );
+    assert!(from_str::<Meters>("3").is_err());
+}
+
+#[test]
+fn test_internally_tagged()
+{
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(tag = "type")]
+    enum Shape
+    {
+        Circle { r: f32 },
+        Square { side: u32 },
+        Empty,
+        Point(MyStruct),
+    }
+
+    assert_eq!(
+        from_str("[{type: Circle, r: 1.5}, {type: \"Square\", side: 2}, {type: Empty}, {type: Point, x: 1, y: 2}]"),
+        Ok(vec![
+            Shape::Circle { r: 1.5 },
+            Shape::Square { side: 2 },
+            Shape::Empty,
+            Shape::Point(MyStruct { x: 1.0, y: 2.0 }),
+        ]),
+    );
+}
+
+#[test]
+fn test_adjacently_tagged()
+{
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(tag = "t", content = "c")]
+    enum Message
+    {
+        Quit,
+        Move(i32, i32),
+        Write(String),
+        Resize { w: u32, h: u32 },
+    }
+
+    assert_eq!(
+        from_str("[{t: Quit}, {t: Move, c: (1, -2)}, {c: \"hi\", t: Write}, {t: Resize, c: {w: 3, h: 4}}]"),
+        Ok(vec![
+            Message::Quit,
+            Message::Move(1, -2),
+            Message::Write("hi".to_owned()),
+            Message::Resize { w: 3, h: 4 },
+        ]),
+    );
+}
+
+#[test]
+fn test_untagged()
+{
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum Number
+    {
+        Unsigned(u8),
+        Signed(i64),
+        Float(f64),
+        Pair(u8, u8),
+        Point { x: f32, y: f32 },
+        Name(String),
+        Nothing,
+    }
+
+    assert_eq!(
+        from_str("[1, -1, 1.5, 1e3, (1, 2), Point { x: 1, y: 2 }, \"a\", ()]"),
+        Ok(vec![
+            Number::Unsigned(1),
+            Number::Signed(-1),
+            Number::Float(1.5),
+            Number::Float(1000.0),
+            Number::Pair(1, 2),
+            Number::Point { x: 1.0, y: 2.0 },
+            Number::Name("a".to_owned()),
+            Number::Nothing,
+        ]),
+    );
+}
+
+#[test]
+fn test_any_drops_struct_names()
+{
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(tag = "type")]
+    enum Tagged
+    {
+        Circle { r: f32 },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape
+    {
+        Circle { r: f32 },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(untagged)]
+    enum Untagged
+    {
+        Shape(Shape),
+    }
+
+    // The name isn't available as a tag.
+    assert!(from_str::<Tagged>("Circle { r: 1.5 }").is_err());
+    assert!(from_str::<Untagged>("Circle { r: 1.5 }").is_err());
+    assert_eq!(from_str("{ Circle: { r: 1.5 } }"), Ok(Untagged::Shape(Shape::Circle { r: 1.5 })));
+}
+
+#[test]
+fn test_flatten()
+{
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config
+    {
+        name: String,
+        #[serde(flatten)]
+        size: Size,
+        #[serde(flatten)]
+        rest: BTreeMap<String, Option<u8>>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Size
+    {
+        w: u32,
+        h: u32,
+    }
+
+    let mut rest = BTreeMap::new();
+    rest.insert("depth".to_owned(), Some(3));
+    rest.insert("parent".to_owned(), None);
+
+    assert_eq!(
+        from_str("Config { name: \"a\", w: 1, h: 2, depth: Some(3), parent: None }"),
+        Ok(Config { name: "a".to_owned(), size: Size { w: 1, h: 2 }, rest }),
+    );
+}
+
+#[test]
+fn test_suggestions()
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Window
+    {
+        title: String,
+        width: u32,
+        kind: Kind,
+    }
+
+    #[derive(Debug, Deserialize)]
+    enum Kind
+    {
+        Normal,
+        Fullscreen,
+    }
+
+    let message = |s| from_str::<Window>(s).unwrap_err().to_string();
+
+    assert_eq!(
+        message("Window {\n    title: \"a\",\n    widht: 1,\n}"),
+        "3:5: Unknown field `widht`, did you mean `width`?",
+    );
+    assert_eq!(
+        message("Window { title: \"a\", width: 1, kind: FullScreen }"),
+        "1:38: Unknown variant `FullScreen`, did you mean `Fullscreen`?",
+    );
+    assert_eq!(
+        message("Window { size: 1 }"),
+        "1:10: Unknown field `size`, expected one of `title`, `width`, `kind`",
+    );
+}
+
+#[test]
+fn test_spanned()
+{
+    #[derive(Debug, Deserialize)]
+    struct Material
+    {
+        texture: Spanned<String>,
+        size: Spanned<MyStruct>,
+        parent: Option<Spanned<u32>>,
+    }
+
+    let material: Material = from_str("Material {\n    texture: \"grass.png\",\n    size: { x: 1, y: 2 }, parent: Some( 7 ),\n}").unwrap();
+
+    assert_eq!(material.texture.get_ref(), "grass.png");
+    assert_eq!((material.texture.start(), material.texture.end()), (Position { line: 2, col: 14 }, Position { line: 2, col: 25 }));
+    assert_eq!(material.size.into_inner(), MyStruct { x: 1.0, y: 2.0 });
+    assert_eq!(material.parent.as_ref().map(Spanned::start), Some(Position { line: 3, col: 41 }));
+    assert_eq!(from_str::<Spanned<u8>>("  1 ").map(|s| (s.start(), s.end())), Ok((Position { line: 1, col: 3 }, Position { line: 1, col: 4 })));
+}
+
+#[test]
+fn test_bindings()
+{
+    use value::Value;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Material
+    {
+        name: String,
+        base: MyStruct,
+        kind: MyEnum,
+        scale: Option<f32>,
+    }
+
+    let materials: Vec<Material> = from_str("
+        let point = { x: 1, y: 2 };
+        let kind = C(true, 1.5);
+        let y = 3;
+        let other = { x: y, y: y };
+
+        [
+            { name: \"a\", base: point, kind: kind, scale: None },
+            { name: \"y\", base: other, kind: A, scale: Some(y) },
+        ]
+    ").unwrap();
+
+    assert_eq!(materials[0].base, MyStruct { x: 1.0, y: 2.0 });
+    assert_eq!(materials[0].kind, MyEnum::C(true, 1.5));
+    assert_eq!(materials[1].base, MyStruct { x: 3.0, y: 3.0 });
+    assert_eq!(materials[1].scale, Some(3.0));
+
+    // Map keys and later bindings aren't references.
+    let value: Value = from_str("let a = \"x\"; let b = \"y\"; { a: b, b: a, c: Some(a) }").unwrap();
+    assert_eq!(value, Value::from_str("{ \"a\": \"y\", \"b\": \"x\", \"c\": Some(\"x\") }").unwrap());
+    assert_eq!(from_str::<Value>("let a = [b]; let b = 1; a"), Ok(Value::from(vec!["b"])));
+    assert_eq!(from_str::<Vec<u8>>("let a = 1; let a = [a, 2]; a"), Ok(vec![1, 2]));
+    assert_eq!(from_str::<Value>("let"), Ok(Value::from("let")));
+
+    assert_eq!(from_str::<u8>("let a = 1 a"), err(ParseError::ExpectedBinding, 1, 11));
+    assert_eq!(from_str::<u8>("let a 1; a"), err(ParseError::ExpectedBinding, 1, 7));
+    assert_eq!(from_str::<bool>("let a = 'x';\nlet b = a;\nb"), err(ParseError::ExpectedBoolean, 3, 1));
+    assert_eq!(from_str::<Vec<bool>>("let a = 1;\n[true,\n a]"), err(ParseError::ExpectedBoolean, 3, 2));
+    assert_eq!(from_str::<u8>("let a = [1;\na"), err(ParseError::UnexpectedByte(';'), 1, 11));
+}
+
+#[test]
+fn test_send_sync()
+{
+    fn check<T: Send + Sync>() {}
+
+    check::<Deserializer>();
+}
+
+/// Every binding doubles the size of the value.
+fn doubling_bindings(n: usize) -> String
+{
+    let mut s = "let a0 = [1, 1];\n".to_owned();
+
+    for i in 1..n {
+        s += &format!("let a{} = [a{1}, a{1}];\n", i, i - 1);
+    }
+
+    s + &format!("a{}", n - 1)
+}
+
+#[test]
+fn test_expansion_limit()
+{
+    use value::Value;
+
+    let value: Value = from_str(&doubling_bindings(4)).unwrap();
+    assert_eq!(value[0][1][0], Value::from(vec![1, 1]));
+
+    match from_str::<Value>(&doubling_bindings(24)) {
+        Err(Error::Parser(ParseError::ExpansionLimit, _)) => {}
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
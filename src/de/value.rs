@@ -1,18 +1,56 @@
-use std::collections::BTreeMap;
 use std::fmt;
+use std::str::FromStr;
 
-use serde::de::{Error, MapAccess, SeqAccess, Visitor};
+use serde::de::{Error, MapAccess, SeqAccess, Unexpected, Visitor};
 use serde::{Deserialize, Deserializer};
 
 use de;
-use value::{Number, Value};
+use value::{Map, Number, Value};
 
 impl Value
 {
-    /// Creates a value from a string reference.
+    /// Creates a value from a string reference, failing on trailing
+    /// characters like `str::parse`.
     pub fn from_str(s: &str) -> de::Result<Self>
     {
-        Self::deserialize(&mut super::Deserializer::from_str(s))
+        de::from_str(s)
+    }
+
+    /// Parses a document, returning the values of its `let` bindings
+    /// in order together with the value itself. References are resolved
+    /// in both.
+    ///
+    /// ```
+    /// use rson_rs::value::Value;
+    ///
+    /// let (bindings, value) = Value::from_str_with_bindings("let gold = 1.0; [gold, 2]").unwrap();
+    ///
+    /// assert_eq!(bindings, vec![("gold".to_owned(), Value::from(1.0))]);
+    /// assert_eq!(value, Value::from_str("[1.0, 2]").unwrap());
+    /// ```
+    pub fn from_str_with_bindings(s: &str) -> de::Result<(Vec<(String, Value)>, Self)>
+    {
+        let mut deserializer = super::Deserializer::from_str(s);
+        let bindings = deserializer
+            .bindings()?
+            .into_iter()
+            .map(|(name, mut value)| Ok((name.to_owned(), Self::deserialize(&mut value)?)))
+            .collect::<de::Result<_>>()?;
+        let value = Self::deserialize(&mut deserializer)?;
+
+        deserializer.end()?;
+
+        Ok((bindings, value))
+    }
+}
+
+impl FromStr for Value
+{
+    type Err = de::Error;
+
+    fn from_str(s: &str) -> de::Result<Self>
+    {
+        Value::from_str(s)
     }
 }
 
@@ -57,6 +95,10 @@ impl<'de> Visitor<'de> for ValueVisitor
     fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
         where E: Error
     {
+        if !v.is_finite() {
+            return Err(E::invalid_value(Unexpected::Float(v), &"a finite number"));
+        }
+
         Ok(Value::Number(Number::new(v)))
     }
 
@@ -133,7 +175,7 @@ impl<'de> Visitor<'de> for ValueVisitor
     fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
         where A: MapAccess<'de>
     {
-        let mut res: BTreeMap<Value, Value> = BTreeMap::new();
+        let mut res = Map::new();
 
         while let Some(entry) = map.next_entry()? {
             res.insert(entry.0, entry.1);
@@ -166,6 +208,35 @@ mod tests
         assert_eq!(eval("Some  (  () )"), Value::Option(Some(Box::new(Value::Unit))));
     }
 
+    #[test]
+    fn test_trailing_characters()
+    {
+        let error = de::Error::Parser(de::ParseError::TrailingCharacters, de::Position { line: 1, col: 3 });
+
+        assert_eq!(Value::from_str("1 2"), Err(error.clone()));
+        assert_eq!("1 2".parse::<Value>(), Err(error));
+    }
+
+    #[test]
+    fn test_non_finite()
+    {
+        assert_eq!(
+            Value::from_str("[1, 1e999]").unwrap_err().to_string(),
+            "invalid value: floating point `inf`, expected a finite number",
+        );
+    }
+
+    #[test]
+    fn test_bindings()
+    {
+        let (bindings, value) = Value::from_str_with_bindings("let a = 1; let b = [a, a]; { a: b }").unwrap();
+
+        assert_eq!(bindings, vec![("a".to_owned(), eval("1")), ("b".to_owned(), eval("[1, 1]"))]);
+        assert_eq!(value, eval("{ a: [1, 1] }"));
+        assert_eq!(Value::from_str_with_bindings("1"), Ok((Vec::new(), eval("1"))));
+        assert!(Value::from_str_with_bindings("let a = 1; a a").is_err());
+    }
+
     #[test]
     fn test_complex()
     {
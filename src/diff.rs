@@ -0,0 +1,257 @@
+//! Structural differences between values.
+//!
+//! Values are compared after parsing, so formatting and comments don't
+//! matter. Map entries are matched by key and sequence elements by
+//! their position. Typed values can be compared with `value::to_value`.
+//!
+//! ```
+//! use rson_rs::diff::diff_str;
+//!
+//! let diff = diff_str(
+//!     "Level { buildings: [{ size: (10, 20) }], title: \"a\" }",
+//!     "// New level\nLevel { buildings: [{ size: (10, 30) }, { floor: 2 }] }",
+//! ).unwrap();
+//!
+//! assert_eq!(diff.to_string(), "\
+//! - buildings[0].size[1]: 20
+//! + buildings[0].size[1]: 30
+//! + buildings[1]: {
+//! +     \"floor\": 2,
+//! + }
+//! - title: \"a\"
+//! ");
+//! ```
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::slice;
+use std::vec;
+
+use de;
+use path::{Path, Segment};
+use value::{key_segment, Value};
+
+/// A single difference, at the path where the values start to differ.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change
+{
+    /// A map entry or sequence element only present in the new value.
+    Added { path: Path, value: Value },
+    /// A map entry or sequence element only present in the old value.
+    Removed { path: Path, value: Value },
+    /// A value which was replaced.
+    Changed { path: Path, old: Value, new: Value },
+}
+
+impl Change
+{
+    pub fn path(&self) -> &Path
+    {
+        match *self {
+            Change::Added { ref path, .. } | Change::Removed { ref path, .. } | Change::Changed { ref path, .. } => path,
+        }
+    }
+}
+
+/// The differences between two values, in the order of the values.
+///
+/// Displaying a diff writes each removed value prefixed with `-` and
+/// each added value prefixed with `+`, as pretty RSON.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Diff
+{
+    changes: Vec<Change>,
+}
+
+impl Diff
+{
+    pub fn changes(&self) -> &[Change]
+    {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.changes.len()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Change>
+    {
+        self.changes.iter()
+    }
+}
+
+impl IntoIterator for Diff
+{
+    type Item = Change;
+    type IntoIter = vec::IntoIter<Change>;
+
+    fn into_iter(self) -> Self::IntoIter
+    {
+        self.changes.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diff
+{
+    type Item = &'a Change;
+    type IntoIter = slice::Iter<'a, Change>;
+
+    fn into_iter(self) -> Self::IntoIter
+    {
+        self.iter()
+    }
+}
+
+impl Display for Diff
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        for change in &self.changes {
+            match *change {
+                Change::Added { ref path, ref value } => write_line(f, '+', path, value)?,
+                Change::Removed { ref path, ref value } => write_line(f, '-', path, value)?,
+                Change::Changed { ref path, ref old, ref new } => {
+                    write_line(f, '-', path, old)?;
+                    write_line(f, '+', path, new)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes `path: value`, prefixing every line of the value with `sign`.
+fn write_line(f: &mut Formatter, sign: char, path: &Path, value: &Value) -> FmtResult
+{
+    let path = if path.is_root() { ".".to_owned() } else { path.to_string() };
+
+    write!(f, "{} {}: ", sign, path)?;
+
+    for (i, line) in value.to_string().lines().enumerate() {
+        if i > 0 {
+            write!(f, "{} ", sign)?;
+        }
+
+        writeln!(f, "{}", line)?;
+    }
+
+    Ok(())
+}
+
+/// Compares two values.
+pub fn diff(old: &Value, new: &Value) -> Diff
+{
+    let mut changes = Vec::new();
+    compare(&mut Path::new(), old, new, &mut changes);
+
+    Diff { changes }
+}
+
+/// Parses and compares two RSON texts.
+pub fn diff_str(old: &str, new: &str) -> de::Result<Diff>
+{
+    Ok(diff(&old.parse()?, &new.parse()?))
+}
+
+fn compare(path: &mut Path, old: &Value, new: &Value, changes: &mut Vec<Change>)
+{
+    if old == new {
+        return;
+    }
+
+    match (old, new) {
+        (Value::Option(Some(old)), Value::Option(Some(new))) => compare(path, old, new, changes),
+        (Value::Map(old_map), Value::Map(new_map))
+            if old_map.keys().chain(new_map.keys()).all(|k| key_segment(k).is_some()) =>
+        {
+            for (key, old) in old_map {
+                path.push(key_segment(key).expect("Bug: checked above"));
+
+                match new_map.get(key) {
+                    Some(new) => compare(path, old, new, changes),
+                    None => changes.push(Change::Removed { path: path.clone(), value: old.clone() }),
+                }
+
+                path.pop();
+            }
+
+            for (key, new) in new_map.iter().filter(|&(k, _)| !old_map.contains_key(k)) {
+                let path = path.join(key_segment(key).expect("Bug: checked above"));
+
+                changes.push(Change::Added { path, value: new.clone() });
+            }
+        }
+        (Value::Seq(old_items), Value::Seq(new_items)) => {
+            for i in 0..old_items.len().max(new_items.len()) {
+                path.push(Segment::Index(i));
+
+                match (old_items.get(i), new_items.get(i)) {
+                    (Some(old), Some(new)) => compare(path, old, new, changes),
+                    (Some(old), None) => changes.push(Change::Removed { path: path.clone(), value: old.clone() }),
+                    (None, Some(new)) => changes.push(Change::Added { path: path.clone(), value: new.clone() }),
+                    (None, None) => unreachable!(),
+                }
+
+                path.pop();
+            }
+        }
+        // Maps with keys which can't be written in a path
+        // are replaced as a whole.
+        _ => changes.push(Change::Changed { path: path.clone(), old: old.clone(), new: new.clone() }),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use value::to_value;
+
+    fn path(s: &str) -> Path
+    {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_diff()
+    {
+        let diff = diff_str(
+            "{ a: Some({ b: 1, c: [1, 2, 3] }), d: { 1: 'x', \"key\": () }, e: { (1, 2): 3 }, f: None }",
+            "{ a: Some({ b: 2, c: [1] }), d: { 1: 'y', \"key\": (), \"a b\": 1 }, e: { (1, 2): 4 }, f: None }",
+        )
+        .unwrap();
+
+        assert_eq!(diff.changes(), &[
+            Change::Changed { path: path("a.b"), old: rson!(1), new: rson!(2) },
+            Change::Removed { path: path("a.c[1]"), value: rson!(2) },
+            Change::Removed { path: path("a.c[2]"), value: rson!(3) },
+            Change::Changed { path: path("d[1]"), old: rson!('x'), new: rson!('y') },
+            Change::Added { path: path("d[\"a b\"]"), value: rson!(1) },
+            Change::Changed { path: path("e"), old: rson!({ (rson!((1, 2))): 3 }), new: rson!({ (rson!((1, 2))): 4 }) },
+        ]);
+        assert!(diff_str("{ a: 1, /* comment */ }", "{\n    // comment\n    a: 1,\n}").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_display()
+    {
+        #[derive(Serialize)]
+        struct Level
+        {
+            name: &'static str,
+            sizes: Vec<u32>,
+        }
+
+        let old = to_value(&Level { name: "a", sizes: vec![1] }).unwrap();
+        let new = to_value(&Level { name: "a", sizes: vec![1, 2] }).unwrap();
+
+        assert_eq!(diff(&old, &new).to_string(), "+ sizes[1]: 2\n");
+        assert_eq!(diff(&rson!(1), &rson!({ a: 'b' })).to_string(), "- .: 1\n+ .: {\n+     \"a\": 'b',\n+ }\n");
+    }
+}
@@ -0,0 +1,807 @@
+//! Format-preserving editing of RSON documents.
+//!
+//! A `Document` keeps the full syntax tree of the text it was parsed from,
+//! so values can be read, changed, added and removed by path while
+//! comments, ordering and formatting of everything else stay untouched.
+//!
+//! ```
+//! use rson_rs::document::Document;
+//!
+//! let mut doc: Document = "{
+//!     // The color of the first building
+//!     color: Yellow,
+//! }".parse().unwrap();
+//!
+//! doc.set("color", &"Red").unwrap();
+//! doc.set("height", &20).unwrap();
+//!
+//! assert_eq!(doc.to_string(), "{
+//!     // The color of the first building
+//!     color: \"Red\",
+//!     height: 20,
+//! }");
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::mem;
+use std::str::FromStr;
+
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+
+use cst::{self, Child, Node, NodeKind, Token, TokenKind};
+use de;
+use parse::is_identifier;
+use path::{ParsePathError, Path, Segment};
+use ser;
+
+/// Document editing result.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// Document editing error.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error
+{
+    /// The document or a value read from it could not be parsed.
+    Parse(de::Error),
+    /// A new value could not be serialized.
+    Serialize(ser::Error),
+    /// The given path is malformed.
+    Path(ParsePathError),
+    /// There is no value at the given path.
+    NotFound(Path),
+    /// The name can't be used as a struct field.
+    InvalidField(String),
+}
+
+impl Display for Error
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        match *self {
+            Error::Parse(ref e) => write!(f, "{}", e),
+            Error::Serialize(ref e) => write!(f, "{}", e),
+            Error::Path(ref e) => write!(f, "{}", e),
+            Error::NotFound(ref path) => write!(f, "No value at `{}`", path),
+            Error::InvalidField(ref name) => write!(f, "`{}` is not a valid field name", name),
+        }
+    }
+}
+
+impl StdError for Error
+{
+}
+
+impl From<de::Error> for Error
+{
+    fn from(e: de::Error) -> Self
+    {
+        Error::Parse(e)
+    }
+}
+
+impl From<ser::Error> for Error
+{
+    fn from(e: ser::Error) -> Self
+    {
+        Error::Serialize(e)
+    }
+}
+
+impl From<ParsePathError> for Error
+{
+    fn from(e: ParsePathError) -> Self
+    {
+        Error::Path(e)
+    }
+}
+
+/// An RSON document which can be edited without losing its formatting.
+///
+/// Paths use the syntax of the `path` module, e.g. `level.buildings[0].color`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document
+{
+    root: Node,
+}
+
+impl Document
+{
+    /// Parses a document from a string.
+    pub fn parse(s: &str) -> de::Result<Self>
+    {
+        Ok(Document { root: cst::parse(s)? })
+    }
+
+    /// Returns the syntax tree of the document.
+    pub fn syntax(&self) -> &Node
+    {
+        &self.root
+    }
+
+    /// Returns true if there is a value at `path`.
+    pub fn contains(&self, path: &str) -> bool
+    {
+        path.parse().ok().and_then(|path| self.locate(&path).ok()).is_some()
+    }
+
+    /// Deserializes the value at `path`, resolving references to the
+    /// `let` bindings of the document.
+    pub fn get<T>(&self, path: &str) -> Result<T>
+        where T: DeserializeOwned
+    {
+        let mut text = String::new();
+
+        for binding in self.root.bindings() {
+            text += &binding.text();
+            text.push('\n');
+        }
+
+        text += &self.get_text(path)?;
+
+        Ok(de::from_str(&text)?)
+    }
+
+    /// Returns the source text of the value at `path`,
+    /// including any comments inside of it.
+    pub fn get_text(&self, path: &str) -> Result<String>
+    {
+        let chain = self.locate(&path.parse()?)?;
+
+        Ok(node_at(&self.root, &chain).text())
+    }
+
+    /// Replaces the value at `path` with `value`.
+    ///
+    /// If the value does not exist yet but its parent is a struct or map,
+    /// a new entry is appended; an index one past the end of a sequence
+    /// appends an element.
+    pub fn set<T>(&mut self, path: &str, value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        let path: Path = path.parse()?;
+
+        let (parent, last) = match path.split_last() {
+            Some((parent, last)) => (parent, last.clone()),
+            None => {
+                let chain = self.locate(&path)?;
+
+                return self.replace(&chain, value);
+            }
+        };
+
+        let chain = self.locate(&parent)?;
+
+        match find_item(node_at(&self.root, &chain), &last) {
+            Some(index) => {
+                let chain = value_chain(&self.root, chain, index);
+
+                self.replace(&chain, value)
+            }
+            None => {
+                let len = node_at(&self.root, &chain).items().count();
+
+                match last {
+                    Segment::Index(i) if i != len => Err(Error::NotFound(path)),
+                    _ => self.add(&chain, &path, &last, None, value),
+                }
+            }
+        }
+    }
+
+    /// Inserts `value` at `path`.
+    ///
+    /// For sequences and tuples the element is inserted before the element
+    /// currently at that index; for struct fields and map keys this does
+    /// the same as `set`.
+    pub fn insert<T>(&mut self, path: &str, value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        let path: Path = path.parse()?;
+
+        match path.split_last() {
+            Some((parent, &Segment::Index(i))) => {
+                let chain = self.locate(&parent)?;
+                let container = node_at(&self.root, &chain);
+                let len = container.items().count();
+
+                match container.kind() {
+                    NodeKind::Seq | NodeKind::Tuple if i <= len => {
+                        self.add(&chain, &path, &Segment::Index(i), Some(i), value)
+                    }
+                    _ => Err(Error::NotFound(path.clone())),
+                }
+            }
+            _ => self.set(&path.to_string(), value),
+        }
+    }
+
+    /// Removes the value at `path` together with its comments.
+    pub fn remove(&mut self, path: &str) -> Result<()>
+    {
+        let path: Path = path.parse()?;
+
+        let (parent, last) = match path.split_last() {
+            Some(split) => split,
+            None => return Err(Error::NotFound(path)),
+        };
+
+        let chain = self.locate(&parent)?;
+        let container = node_at_mut(&mut self.root, &chain);
+
+        match find_item(container, last) {
+            Some(index) => {
+                remove_item(container, index);
+
+                Ok(())
+            }
+            None => Err(Error::NotFound(path.clone())),
+        }
+    }
+
+    /// Returns the chain of child indices leading to the value at `path`.
+    fn locate(&self, path: &Path) -> Result<Vec<usize>>
+    {
+        let mut chain = vec![value_index(&self.root)];
+
+        for (i, segment) in path.segments().iter().enumerate() {
+            match find_item(node_at(&self.root, &chain), segment) {
+                Some(index) => chain = value_chain(&self.root, chain, index),
+                None => {
+                    let found = path.segments()[..=i].to_vec();
+
+                    return Err(Error::NotFound(found.into()));
+                }
+            }
+        }
+
+        Ok(chain)
+    }
+
+    fn replace<T>(&mut self, chain: &[usize], value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        let indent = line_indent(&self.root, chain);
+        let node = render(value, &indent)?;
+
+        let (last, parent) = chain.split_last().expect("Bug: empty chain");
+        node_at_mut(&mut self.root, parent).children_mut()[*last] = Child::Node(node);
+
+        Ok(())
+    }
+
+    /// Adds a new item to the collection at `chain`.
+    fn add<T>(
+        &mut self,
+        chain: &[usize],
+        path: &Path,
+        segment: &Segment,
+        position: Option<usize>,
+        value: &T,
+    ) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        let container_indent = line_indent(&self.root, chain);
+        let container = node_at_mut(&mut self.root, chain);
+        let indent = item_indent(container, &container_indent);
+
+        let item = match (container.kind(), segment) {
+            (NodeKind::Struct, Segment::Field(name)) => {
+                if !is_identifier(name) {
+                    return Err(Error::InvalidField(name.clone()));
+                }
+
+                let key = Child::Token(Token::new(TokenKind::Ident, name));
+
+                entry(key, render(value, &indent)?)
+            }
+            (NodeKind::Map, Segment::Field(name)) if container.items().next().is_none() && is_identifier(name) => {
+                // An empty `{}` is parsed as a map, but a field name says it's a struct.
+                let children = mem::take(container.children_mut());
+                *container = Node::new(NodeKind::Struct, children);
+
+                let key = Child::Token(Token::new(TokenKind::Ident, name));
+
+                entry(key, render(value, &indent)?)
+            }
+            (NodeKind::Map, Segment::Field(name)) => {
+                let key = Child::Node(render(name, &indent)?);
+
+                entry(key, render(value, &indent)?)
+            }
+            (NodeKind::Map, &Segment::Index(i)) => {
+                let key = Child::Node(render(&i, &indent)?);
+
+                entry(key, render(value, &indent)?)
+            }
+            (NodeKind::Seq, &Segment::Index(_)) | (NodeKind::Tuple, &Segment::Index(_)) => {
+                render(value, &indent)?
+            }
+            _ => return Err(Error::NotFound(path.clone())),
+        };
+
+        insert_item(container, position, item, &indent, &container_indent);
+
+        Ok(())
+    }
+}
+
+impl FromStr for Document
+{
+    type Err = de::Error;
+
+    fn from_str(s: &str) -> de::Result<Self>
+    {
+        Document::parse(s)
+    }
+}
+
+impl Display for Document
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        self.root.fmt(f)
+    }
+}
+
+fn node_at<'a>(root: &'a Node, chain: &[usize]) -> &'a Node
+{
+    chain.iter().fold(root, |node, &i| {
+        node.children()[i].as_node().expect("Bug: chain points to a token")
+    })
+}
+
+fn node_at_mut<'a>(root: &'a mut Node, chain: &[usize]) -> &'a mut Node
+{
+    chain.iter().fold(root, |node, &i| {
+        node.children_mut()[i].as_node_mut().expect("Bug: chain points to a token")
+    })
+}
+
+fn value_index(node: &Node) -> usize
+{
+    node.children().iter().rposition(|c| c.as_node().is_some()).expect("Bug: node without a value")
+}
+
+/// Extends `chain` to the item at `index` of the node at `chain`,
+/// stepping into the value if the item is an entry.
+fn value_chain(root: &Node, mut chain: Vec<usize>, index: usize) -> Vec<usize>
+{
+    chain.push(index);
+
+    let item = node_at(root, &chain);
+    if item.kind() == NodeKind::Entry {
+        chain.push(value_index(item));
+    }
+
+    chain
+}
+
+fn is_token(child: Option<&Child>, kind: TokenKind) -> bool
+{
+    child.and_then(Child::as_token).map(|t| t.kind() == kind).unwrap_or(false)
+}
+
+fn is_comment(child: Option<&Child>) -> bool
+{
+    child.and_then(Child::as_token).map(|t| t.kind().is_comment()).unwrap_or(false)
+}
+
+fn is_inline_space(child: Option<&Child>) -> bool
+{
+    child
+        .and_then(Child::as_token)
+        .map(|t| t.kind() == TokenKind::Whitespace && !t.text().contains('\n'))
+        .unwrap_or(false)
+}
+
+/// Returns the index of the item selected by `segment` in `container`.
+fn find_item(container: &Node, segment: &Segment) -> Option<usize>
+{
+    let mut items = container
+        .children()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| c.as_node().map(|n| (i, n)));
+
+    match (container.kind(), segment) {
+        (NodeKind::Struct, Segment::Field(name)) => items
+            .find(|&(_, entry)| {
+                entry.key().and_then(Child::as_token).map(|t| t.text() == name).unwrap_or(false)
+            })
+            .map(|(i, _)| i),
+        (NodeKind::Map, segment) => items
+            .find(|&(_, entry)| {
+                let key = entry.key().and_then(Child::as_node).and_then(Node::token);
+
+                match (key, segment) {
+                    (Some(key), Segment::Field(name)) => match key.kind() {
+                        TokenKind::String => de::from_str::<String>(key.text()).ok().as_ref() == Some(name),
+                        TokenKind::Ident => key.text() == name,
+                        _ => false,
+                    },
+                    (Some(key), &Segment::Index(i)) => {
+                        key.kind() == TokenKind::Number && de::from_str::<u64>(key.text()) == Ok(i as u64)
+                    }
+                    (None, _) => false,
+                }
+            })
+            .map(|(i, _)| i),
+        (NodeKind::Seq, &Segment::Index(i)) | (NodeKind::Tuple, &Segment::Index(i)) => {
+            items.nth(i).map(|(i, _)| i)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the indentation of the line the node at `chain` starts on.
+fn line_indent(root: &Node, chain: &[usize]) -> String
+{
+    let mut prefix = String::new();
+    let mut node = root;
+
+    for &i in chain {
+        for child in &node.children()[..i] {
+            match *child {
+                Child::Token(ref t) => prefix.push_str(t.text()),
+                Child::Node(ref n) => prefix.push_str(&n.text()),
+            }
+        }
+
+        node = node.children()[i].as_node().expect("Bug: chain points to a token");
+    }
+
+    let line = prefix.rsplit('\n').next().unwrap_or("");
+
+    line.chars().take_while(|&c| c == ' ' || c == '\t').collect()
+}
+
+/// Guesses the indentation for items of `container` from its first item.
+fn item_indent(container: &Node, container_indent: &str) -> String
+{
+    let mut last_space = None;
+
+    for child in container.children() {
+        match *child {
+            Child::Token(ref t) if t.kind() == TokenKind::Whitespace => last_space = Some(t.text()),
+            Child::Token(_) => {}
+            Child::Node(_) => break,
+        }
+    }
+
+    match last_space.and_then(|s| s.rfind('\n').map(|i| &s[i + 1..])) {
+        Some(indent) => indent.to_owned(),
+        None => format!("{}    ", container_indent),
+    }
+}
+
+/// Serializes `value` in the pretty layout, indenting all but
+/// the first line by `indent`.
+fn render<T>(value: &T, indent: &str) -> Result<Node>
+    where T: ?Sized + Serialize
+{
+    let text = ser::pretty::to_string(&value)?;
+    let mut indented = String::with_capacity(text.len());
+
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            indented.push('\n');
+
+            if !line.is_empty() {
+                indented.push_str(indent);
+            }
+        }
+
+        indented.push_str(line);
+    }
+
+    Ok(cst::parse_value(&indented)?)
+}
+
+fn entry(key: Child, value: Node) -> Node
+{
+    Node::new(NodeKind::Entry, vec![
+        key,
+        Child::Token(Token::new(TokenKind::Colon, ":")),
+        Child::Token(Token::new(TokenKind::Whitespace, " ")),
+        Child::Node(value),
+    ])
+}
+
+fn comma() -> Child
+{
+    Child::Token(Token::new(TokenKind::Comma, ","))
+}
+
+fn space(text: &str) -> Child
+{
+    Child::Token(Token::new(TokenKind::Whitespace, text))
+}
+
+/// Inserts `item` before the item number `position`, or after the last
+/// item, following the comma style of the existing items.
+fn insert_item(
+    container: &mut Node,
+    position: Option<usize>,
+    item: Node,
+    indent: &str,
+    container_indent: &str,
+)
+{
+    let multiline = container.text().contains('\n');
+    let items: Vec<usize> = container
+        .children()
+        .iter()
+        .enumerate()
+        .filter(|&(_, c)| c.as_node().is_some())
+        .map(|(i, _)| i)
+        .collect();
+    let children = container.children_mut();
+
+    if let Some(&index) = position.and_then(|p| items.get(p)) {
+        let separator = match children[index - 1] {
+            Child::Token(ref t) if t.kind() == TokenKind::Whitespace => t.text().to_owned(),
+            _ => " ".to_owned(),
+        };
+
+        let new = vec![Child::Node(item), comma(), space(&separator)];
+        children.splice(index..index, new);
+
+        return;
+    }
+
+    let last = match items.last() {
+        Some(&last) => last,
+        None => {
+            let is_struct = item.kind() == NodeKind::Entry;
+
+            if !children.iter().any(|c| is_comment(Some(c))) {
+                children.retain(|c| !c.is_trivia());
+            }
+
+            let open = children
+                .iter()
+                .position(|c| c.as_token().map(|t| !t.kind().is_trivia() && t.kind() != TokenKind::Ident).unwrap_or(false))
+                .expect("Bug: collection without an opening bracket");
+
+            let new = if is_struct || multiline {
+                vec![
+                    space(&format!("\n{}", indent)),
+                    Child::Node(item),
+                    comma(),
+                    space(&format!("\n{}", container_indent)),
+                ]
+            } else {
+                vec![Child::Node(item)]
+            };
+
+            children.splice(open + 1..open + 1, new);
+
+            return;
+        }
+    };
+
+    let next = (last + 1..children.len()).find(|&i| !children[i].is_trivia()).unwrap_or(last + 1);
+    let trailing_comma = is_token(children.get(next), TokenKind::Comma);
+
+    let mut at = if trailing_comma {
+        next + 1
+    } else {
+        children.insert(last + 1, comma());
+
+        last + 2
+    };
+
+    if is_inline_space(children.get(at)) && is_comment(children.get(at + 1)) {
+        at += 2;
+    } else if is_comment(children.get(at)) {
+        at += 1;
+    }
+
+    let separator = if multiline { format!("\n{}", indent) } else { " ".to_owned() };
+    let mut new = vec![space(&separator), Child::Node(item)];
+
+    if trailing_comma {
+        new.push(comma());
+    }
+
+    children.splice(at..at, new);
+}
+
+/// Removes the item at child `index` along with its separating comma,
+/// the comments on the lines before it and a comment on its own line.
+fn remove_item(container: &mut Node, index: usize)
+{
+    let children = container.children_mut();
+
+    let previous = (0..index).rev().find(|&i| !children[i].is_trivia()).expect("Bug: item without an opening bracket");
+    let mut start = previous + 1;
+
+    if is_inline_space(children.get(start)) && is_comment(children.get(start + 1)) {
+        start += 2;
+    } else if is_comment(children.get(start)) {
+        start += 1;
+    }
+
+    let start = start.min(index);
+
+    let next = (index + 1..children.len()).find(|&i| !children[i].is_trivia()).unwrap_or(index + 1);
+    let has_comma = is_token(children.get(next), TokenKind::Comma);
+
+    let mut end = index + 1;
+
+    if has_comma {
+        end = next + 1;
+
+        if is_inline_space(children.get(end)) && is_comment(children.get(end + 1)) {
+            end += 2;
+        } else if is_comment(children.get(end)) {
+            end += 1;
+        }
+    }
+
+    if !is_comment(children.get(previous)) && !is_token(children.get(previous), TokenKind::Comma)
+        && is_inline_space(children.get(end))
+    {
+        end += 1;
+    }
+
+    children.drain(start..end);
+
+    if !has_comma && is_token(children.get(previous), TokenKind::Comma) {
+        children.remove(previous);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const LEVEL: &str = "// The first level
+Level {
+    buildings: [
+        {
+            size: (10, 20),
+            color: Yellow, // This is an enum variant
+            owner: None,
+        },
+        /* The second one */
+        {
+            size: (20, 25),
+            color: Custom(0.1, 0.8, 1.0),
+            owner: Some(\"guy\"),
+        },
+    ],
+    characters: {
+        \"guy\": {
+            friendly: true,
+        },
+    },
+}
+";
+
+    fn level() -> Document
+    {
+        LEVEL.parse().expect("Failed to parse document")
+    }
+
+    #[test]
+    fn test_unchanged()
+    {
+        assert_eq!(level().to_string(), LEVEL);
+    }
+
+    #[test]
+    fn test_get()
+    {
+        let doc = level();
+
+        assert_eq!(doc.get::<(u32, u32)>("buildings[1].size"), Ok((20, 25)));
+        assert_eq!(doc.get::<bool>("characters.guy.friendly"), Ok(true));
+        assert_eq!(doc.get::<Option<String>>("buildings.1.owner"), Ok(Some("guy".to_owned())));
+        assert_eq!(doc.get_text("buildings[0].color"), Ok("Yellow".to_owned()));
+        assert_eq!(doc.get_text("buildings[2]"), Err(Error::NotFound("buildings[2]".parse().unwrap())));
+        assert!(!doc.contains("characters.guy.enemy"));
+    }
+
+    #[test]
+    fn test_bindings()
+    {
+        let mut doc: Document = "let red = (255, 0, 0);\nlet a = [red];\n\n{ color: red, all: a }".parse().unwrap();
+
+        assert_eq!(doc.get::<(u8, u8, u8)>("color"), Ok((255, 0, 0)));
+        assert_eq!(doc.get::<Vec<(u8, u8, u8)>>("all"), Ok(vec![(255, 0, 0)]));
+        assert_eq!(doc.get_text("color"), Ok("red".to_owned()));
+
+        doc.set("color", &(0, 0, 255)).unwrap();
+
+        assert_eq!(doc.to_string(), "let red = (255, 0, 0);\nlet a = [red];\n\n{ color: (0, 0, 255), all: a }");
+    }
+
+    #[test]
+    fn test_set()
+    {
+        let mut doc = level();
+
+        doc.set("buildings[0].size", &(15, 30)).unwrap();
+        doc.set("buildings[0].owner", &Some("girl")).unwrap();
+        doc.set("characters.guy.health", &100).unwrap();
+        doc.set("characters[\"girl\"]", &vec![1, 2]).unwrap();
+
+        assert_eq!(doc.to_string(), "// The first level
+Level {
+    buildings: [
+        {
+            size: (15, 30),
+            color: Yellow, // This is an enum variant
+            owner: Some(\"girl\"),
+        },
+        /* The second one */
+        {
+            size: (20, 25),
+            color: Custom(0.1, 0.8, 1.0),
+            owner: Some(\"guy\"),
+        },
+    ],
+    characters: {
+        \"guy\": {
+            friendly: true,
+            health: 100,
+        },
+        \"girl\": [
+            1,
+            2,
+        ],
+    },
+}
+");
+    }
+
+    #[test]
+    fn test_insert()
+    {
+        let mut doc: Document = "[1, 2] // numbers".parse().unwrap();
+
+        doc.insert("[0]", &0).unwrap();
+        doc.insert("[3]", &3).unwrap();
+        doc.set("[4]", &4).unwrap();
+        assert_eq!(doc.to_string(), "[0, 1, 2, 3, 4] // numbers");
+
+        assert_eq!(doc.insert("[6]", &6), Err(Error::NotFound("[6]".parse().unwrap())));
+
+        let mut doc: Document = "{}".parse().unwrap();
+        doc.set("x", &1).unwrap();
+        assert_eq!(doc.to_string(), "{\n    x: 1,\n}");
+        assert_eq!(doc.set("[\"not a field\"]", &1), Err(Error::InvalidField("not a field".to_owned())));
+    }
+
+    #[test]
+    fn test_remove()
+    {
+        let mut doc = level();
+
+        doc.remove("buildings[1]").unwrap();
+        doc.remove("buildings[0].color").unwrap();
+        doc.remove("characters.guy").unwrap();
+
+        assert_eq!(doc.to_string(), "// The first level
+Level {
+    buildings: [
+        {
+            size: (10, 20),
+            owner: None,
+        },
+    ],
+    characters: {
+    },
+}
+");
+
+        let mut doc: Document = "(1, 2, 3)".parse().unwrap();
+        doc.remove("2").unwrap();
+        doc.remove("0").unwrap();
+        assert_eq!(doc.to_string(), "(2)");
+    }
+}
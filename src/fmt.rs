@@ -0,0 +1,587 @@
+//! Canonical formatting of RSON text.
+//!
+//! The formatter works on the lossless syntax tree, so unlike
+//! `ser::pretty` it doesn't need typed data and keeps all comments.
+//! The layout it produces is:
+//!
+//! * structs and maps with entries are spread over multiple lines,
+//! * sequences and tuples stay on one line if they fit into the
+//!   maximum width and contain no comments,
+//! * items of multi-line collections get a trailing comma,
+//! * one space after colons and commas, none before them,
+//! * at most one blank line is kept between items,
+//! * every `let` binding starts on a new line.
+//!
+//! ```
+//! use rson_rs::fmt::format;
+//!
+//! assert_eq!(
+//!     format("Scene{size:(10,20),tags:[\"a\"] // the tags\n}").unwrap(),
+//!     "Scene {\n    size: (10, 20),\n    tags: [\"a\"], // the tags\n}\n",
+//! );
+//! ```
+
+use std::mem;
+
+use cst::{self, Child, Node, NodeKind, Token, TokenKind};
+use de::Result;
+
+/// Formatting options.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config
+{
+    /// The maximum line width for putting short collections on one line.
+    pub max_width: usize,
+    /// The string used for one level of indentation.
+    pub indent: String,
+}
+
+impl Default for Config
+{
+    fn default() -> Self
+    {
+        Config {
+            max_width: 100,
+            indent: "    ".to_owned(),
+        }
+    }
+}
+
+/// Formats `input` with the default `Config`.
+pub fn format(input: &str) -> Result<String>
+{
+    format_with_config(input, &Config::default())
+}
+
+/// Formats `input` according to `config`.
+pub fn format_with_config(input: &str, config: &Config) -> Result<String>
+{
+    let document = cst::parse(input)?;
+    let mut f = Formatter {
+        config,
+        out: String::new(),
+        level: 0,
+    };
+
+    f.document(&document);
+
+    Ok(f.out)
+}
+
+/// Removes all comments, trailing commas and whitespace which isn't needed.
+pub fn minify(input: &str) -> Result<String>
+{
+    let mut out = String::new();
+
+    minify_node(&cst::parse(input)?, &mut out, &mut None);
+
+    Ok(out)
+}
+
+fn minify_node(node: &Node, out: &mut String, last: &mut Option<TokenKind>)
+{
+    for child in node.children() {
+        match *child {
+            Child::Node(ref node) => minify_node(node, out, last),
+            Child::Token(ref token) if token.kind().is_trivia() => {}
+            Child::Token(ref token) => {
+                if matches!(*last, Some(k) if is_word(k)) && is_word(token.kind()) {
+                    out.push(' ');
+                }
+
+                if *last == Some(TokenKind::Comma) && is_close(token.kind()) {
+                    out.pop();
+                }
+
+                out.push_str(token.text());
+                *last = Some(token.kind());
+            }
+        }
+    }
+}
+
+fn is_close(kind: TokenKind) -> bool
+{
+    matches!(kind, TokenKind::CloseBrace | TokenKind::CloseParen | TokenKind::CloseBracket)
+}
+
+fn is_word(kind: TokenKind) -> bool
+{
+    matches!(kind, TokenKind::Ident | TokenKind::Number | TokenKind::String | TokenKind::Char)
+}
+
+struct Comment<'a>
+{
+    text: &'a str,
+    blank_before: bool,
+}
+
+struct Item<'a>
+{
+    leading: Vec<Comment<'a>>,
+    blank_before: bool,
+    node: &'a Node,
+    trailing: Vec<&'a str>,
+}
+
+/// A collection split into the parts which matter for the layout.
+struct Collection<'a>
+{
+    name: Option<&'a str>,
+    open: &'a str,
+    close: &'a str,
+    head: Vec<Comment<'a>>,
+    items: Vec<Item<'a>>,
+    dangling: Vec<Comment<'a>>,
+}
+
+impl<'a> Collection<'a>
+{
+    fn new(node: &'a Node) -> Self
+    {
+        let mut collection = Collection {
+            name: node.name(),
+            open: "",
+            close: "",
+            head: Vec::new(),
+            items: Vec::new(),
+            dangling: Vec::new(),
+        };
+
+        let mut pending = Vec::new();
+        let mut newlines = 0;
+        let mut same_line = false;
+
+        for child in node.children() {
+            let token = match *child {
+                Child::Node(ref node) => {
+                    collection.items.push(Item {
+                        leading: mem::take(&mut pending),
+                        blank_before: newlines > 1,
+                        node,
+                        trailing: Vec::new(),
+                    });
+
+                    newlines = 0;
+                    same_line = true;
+
+                    continue;
+                }
+                Child::Token(ref token) => token,
+            };
+
+            match token.kind() {
+                TokenKind::Whitespace => {
+                    let n = token.text().matches('\n').count();
+
+                    newlines += n;
+                    same_line = same_line && n == 0;
+                }
+                kind if kind.is_comment() => {
+                    if same_line {
+                        let last = collection.items.len() - 1;
+                        collection.items[last].trailing.push(token.text());
+                    } else {
+                        let comment = Comment {
+                            text: token.text(),
+                            blank_before: newlines > 1,
+                        };
+
+                        if collection.open.is_empty() {
+                            collection.head.push(comment);
+                        } else {
+                            pending.push(comment);
+                        }
+
+                        newlines = 0;
+                    }
+                }
+                TokenKind::OpenBrace | TokenKind::OpenParen | TokenKind::OpenBracket => {
+                    collection.open = token.text();
+                    collection.head.append(&mut pending);
+                    newlines = 0;
+                }
+                TokenKind::CloseBrace | TokenKind::CloseParen | TokenKind::CloseBracket => {
+                    collection.close = token.text();
+                }
+                _ => {}
+            }
+        }
+
+        collection.dangling = pending;
+
+        collection
+    }
+
+    fn has_comments(&self) -> bool
+    {
+        !self.head.is_empty() || !self.dangling.is_empty()
+            || self.items.iter().any(|i| !i.leading.is_empty() || !i.trailing.is_empty())
+    }
+}
+
+struct Formatter<'a>
+{
+    config: &'a Config,
+    out: String,
+    level: usize,
+}
+
+impl<'a> Formatter<'a>
+{
+    fn column(&self) -> usize
+    {
+        self.out.len() - self.out.rfind('\n').map(|i| i + 1).unwrap_or(0)
+    }
+
+    fn newline(&mut self, blank: bool)
+    {
+        if blank {
+            self.out.push('\n');
+        }
+
+        self.out.push('\n');
+
+        for _ in 0..self.level {
+            self.out.push_str(&self.config.indent);
+        }
+    }
+
+    fn document(&mut self, document: &Node)
+    {
+        let mut newlines = 0;
+        let mut before_value = true;
+        let mut first = true;
+
+        for child in document.children() {
+            match *child {
+                Child::Node(ref node) => {
+                    if !first {
+                        self.newline(newlines > 1);
+                    }
+
+                    if node.kind() == NodeKind::Binding {
+                        for comment in self.binding(node) {
+                            self.out.push(' ');
+                            self.out.push_str(comment);
+                        }
+                    } else {
+                        self.value(node, 0);
+                    }
+
+                    before_value = false;
+                    first = false;
+                    newlines = 0;
+                }
+                Child::Token(ref t) if t.kind() == TokenKind::Whitespace => {
+                    newlines += t.text().matches('\n').count();
+                }
+                Child::Token(ref t) => {
+                    if first {
+                        self.out.push_str(t.text());
+                    } else if !before_value && newlines == 0 {
+                        self.out.push(' ');
+                        self.out.push_str(t.text());
+                    } else {
+                        self.newline(newlines > 1);
+                        self.out.push_str(t.text());
+                    }
+
+                    first = false;
+                    newlines = 0;
+                }
+            }
+        }
+
+        self.out.push('\n');
+    }
+
+    /// Writes `node`, keeping `reserve` columns free after it.
+    fn value(&mut self, node: &Node, reserve: usize)
+    {
+        if let Some(flat) = flat(node) {
+            if self.column() + flat.len() + reserve <= self.config.max_width
+                || matches!(node.kind(), NodeKind::Literal | NodeKind::Include)
+            {
+                self.out.push_str(&flat);
+
+                return;
+            }
+        }
+
+        match node.kind() {
+            NodeKind::Literal | NodeKind::Include => self.out.push_str(&node.text()),
+            _ => self.block(&Collection::new(node)),
+        }
+    }
+
+    fn block(&mut self, collection: &Collection)
+    {
+        if let Some(name) = collection.name {
+            self.out.push_str(name);
+
+            if collection.open == "{" {
+                self.out.push(' ');
+            }
+        }
+
+        self.out.push_str(collection.open);
+        self.level += 1;
+
+        let mut first = true;
+
+        for comment in &collection.head {
+            self.comment(comment, first);
+            first = false;
+        }
+
+        for item in &collection.items {
+            for comment in &item.leading {
+                self.comment(comment, first);
+                first = false;
+            }
+
+            self.newline(item.blank_before && !first);
+            first = false;
+
+            let trailing = self.item(item.node);
+            self.out.push(',');
+
+            for comment in trailing.iter().chain(&item.trailing) {
+                self.out.push(' ');
+                self.out.push_str(comment);
+            }
+        }
+
+        for comment in &collection.dangling {
+            self.comment(comment, first);
+            first = false;
+        }
+
+        self.level -= 1;
+        self.newline(false);
+        self.out.push_str(collection.close);
+    }
+
+    fn comment(&mut self, comment: &Comment, first: bool)
+    {
+        self.newline(comment.blank_before && !first);
+        self.out.push_str(comment.text);
+    }
+
+    /// Writes an item of a collection and returns the comments inside of
+    /// an entry, which are moved behind it.
+    fn item<'n>(&mut self, item: &'n Node) -> Vec<&'n str>
+    {
+        if item.kind() != NodeKind::Entry {
+            self.value(item, 1);
+
+            return Vec::new();
+        }
+
+        let mut comments = Vec::new();
+
+        for child in item.children() {
+            match *child {
+                Child::Token(ref t) if t.kind().is_comment() => comments.push(t.text()),
+                Child::Token(ref t) if t.kind() == TokenKind::Ident => self.out.push_str(t.text()),
+                Child::Token(ref t) if t.kind() == TokenKind::Colon => self.out.push_str(": "),
+                Child::Token(_) => {}
+                Child::Node(ref n) => self.value(n, 1),
+            }
+        }
+
+        comments
+    }
+
+    /// Writes `let name = value;` and returns the comments inside of it,
+    /// which are moved behind it.
+    fn binding<'n>(&mut self, binding: &'n Node) -> Vec<&'n str>
+    {
+        let mut comments = Vec::new();
+
+        for child in binding.children() {
+            match *child {
+                Child::Token(ref t) if t.kind().is_comment() => comments.push(t.text()),
+                Child::Token(ref t) if t.kind() == TokenKind::Ident => {
+                    self.out.push_str(t.text());
+                    self.out.push(' ');
+                }
+                Child::Token(ref t) if t.kind() == TokenKind::Equals => self.out.push_str("= "),
+                Child::Token(ref t) if t.kind() == TokenKind::Semicolon => self.out.push(';'),
+                Child::Token(_) => {}
+                Child::Node(ref n) => self.value(n, 1),
+            }
+        }
+
+        comments
+    }
+}
+
+/// Renders `node` on a single line, if its layout allows that.
+fn flat(node: &Node) -> Option<String>
+{
+    match node.kind() {
+        NodeKind::Literal => node.token().map(Token::text).filter(|t| !t.contains('\n')).map(str::to_owned),
+        NodeKind::Include if !node.tokens().any(|t| t.kind().is_comment()) => {
+            let file = node.tokens().find(|t| t.kind() == TokenKind::String).map(Token::text)?;
+
+            Some(format!("include!({})", file)).filter(|s| !s.contains('\n'))
+        }
+        NodeKind::Tuple | NodeKind::Seq | NodeKind::Struct | NodeKind::Map => {
+            let collection = Collection::new(node);
+            let is_struct = node.kind() == NodeKind::Struct || node.kind() == NodeKind::Map;
+
+            if collection.has_comments() || (is_struct && !collection.items.is_empty()) {
+                return None;
+            }
+
+            let items = collection
+                .items
+                .iter()
+                .map(|i| flat(i.node))
+                .collect::<Option<Vec<_>>>()?;
+
+            let mut s = String::new();
+
+            if let Some(name) = collection.name {
+                s.push_str(name);
+
+                if is_struct {
+                    s.push(' ');
+                }
+            }
+
+            s.push_str(collection.open);
+            s.push_str(&items.join(", "));
+            s.push_str(collection.close);
+
+            Some(s)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn check(input: &str, expected: &str)
+    {
+        let formatted = format(input).expect("Failed to format");
+
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), formatted, "Formatting is not idempotent");
+    }
+
+    #[test]
+    fn test_layout()
+    {
+        check("Game{title:\"Hello\",level:Level{},size:(1,2,),tags:[],map:{1:'1'},none:None}", "Game {
+    title: \"Hello\",
+    level: Level {},
+    size: (1, 2),
+    tags: [],
+    map: {
+        1: '1',
+    },
+    none: None,
+}
+");
+        check("  ( 1 ,Some( 2 ) )  ", "(1, Some(2))\n");
+    }
+
+    #[test]
+    fn test_comments()
+    {
+        check("/* header */
+
+// more
+{ // about x
+  x: 1, // one
+
+
+  /* y */ y: [1,
+  2], z: (3 /* three */)
+  // the end
+} // done
+// really", "/* header */
+
+// more
+{
+    // about x
+    x: 1, // one
+
+    /* y */
+    y: [1, 2],
+    z: (
+        3, /* three */
+    ),
+    // the end
+} // done
+// really
+");
+    }
+
+    #[test]
+    fn test_width()
+    {
+        let config = Config {
+            max_width: 20,
+            indent: "  ".to_owned(),
+        };
+
+        assert_eq!(
+            format_with_config("{ short: [1, 2], long: [\"first\", \"second\"] }", &config).unwrap(),
+            "{\n  short: [1, 2],\n  long: [\n    \"first\",\n    \"second\",\n  ],\n}\n",
+        );
+    }
+
+    #[test]
+    fn test_minify()
+    {
+        assert_eq!(
+            minify("Game { // x\n  title: \"a b\",\n  size: (1, 2),\n  owner: Some(r\"x\"),\n}").unwrap(),
+            "Game{title:\"a b\",size:(1,2),owner:Some(r\"x\")}",
+        );
+    }
+
+    #[test]
+    fn test_include()
+    {
+        check("{a:include! ( \"a.rson\" ),b:[include!(r\"b\")]}", "{\n    a: include!(\"a.rson\"),\n    b: [include!(r\"b\")],\n}\n");
+        check("include!(\"a\" /* a */)", "include!(\"a\" /* a */)\n");
+        assert_eq!(minify("[ include!( \"a\" ) ]").unwrap(), "[include!(\"a\")]");
+    }
+
+    #[test]
+    fn test_bindings()
+    {
+        check("let a=1;let b /* b */ = [a,a] ;\n\n\n// the value\n{x:b}", "let a = 1;
+let b = [a, a]; /* b */
+
+// the value
+{
+    x: b,
+}
+");
+        check("let metal = { shiny: true }; // metal\n[metal]", "let metal = {
+    shiny: true,
+}; // metal
+[metal]
+");
+        assert_eq!(minify("let a = 1;\nlet b = [a, a];\n[b]").unwrap(), "let a=1;let b=[a,a];[b]");
+    }
+
+    #[test]
+    fn test_example()
+    {
+        let example = include_str!("../examples/example.rson");
+        let formatted = format(example).unwrap();
+
+        assert_eq!(format(&formatted).unwrap(), formatted);
+        assert!(formatted.contains("/*/*/* Try it yourself! */*/*/"));
+        assert!(formatted.contains("    tuple: (3, 7),\n"));
+    }
+}
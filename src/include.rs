@@ -0,0 +1,496 @@
+//! Assembling one document from several files with `include!("file")`.
+//!
+//! An include directive can stand anywhere a value can, and is replaced
+//! by the content of the file, before parsing. Files are read through a
+//! `Loader`, so they can come from the file system (`FsLoader`) or from
+//! anywhere else. Errors are reported in the file they occur in. Only
+//! the first file can enable extensions with `#![enable(..)]`.
+//!
+//! ```
+//! use std::collections::HashMap;
+//!
+//! use rson_rs::include::{from_file, Error};
+//! use rson_rs::value::Value;
+//!
+//! let mut files = HashMap::new();
+//! files.insert("scene.rson".to_owned(), "{ materials: include!(\"materials.rson\"), size: 2 }".to_owned());
+//! files.insert("materials.rson".to_owned(), "{\n    metal: 1.0,\n    wood: 0.2,\n}".to_owned());
+//!
+//! let scene: Value = from_file("scene.rson", &mut files).unwrap();
+//! assert_eq!(scene["materials"]["wood"].as_f64(), Some(0.2));
+//!
+//! files.insert("materials.rson".to_owned(), "{\n    metal: 1.0,\n    wood: [,\n}".to_owned());
+//!
+//! let error = from_file::<Value, _>("scene.rson", &mut files).unwrap_err();
+//!
+//! assert!(matches!(error, Error::Parse { ref file, .. } if file == "materials.rson"));
+//! assert_eq!(error.to_string(), "materials.rson:3:12: Unexpected byte");
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+
+use cst::lexer::next_token;
+use cst::TokenKind;
+use de::{self, ParseError, Position};
+use parse::{Bytes, ParsedStr};
+
+/// Include result.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// The error returned when a document can't be assembled or parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error
+{
+    /// A file could not be loaded.
+    Load { file: String, message: String },
+    /// A file could not be parsed, with the position in that file.
+    Parse { file: String, error: de::Error },
+    /// A file includes itself, directly or through other files.
+    Cycle(String),
+}
+
+impl Display for Error
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        match *self {
+            Error::Load { ref file, ref message } => write!(f, "Failed to load `{}`: {}", file, message),
+            Error::Parse { ref file, ref error } => write!(f, "{}:{}", file, error),
+            Error::Cycle(ref file) => write!(f, "`{}` includes itself", file),
+        }
+    }
+}
+
+impl StdError for Error
+{
+}
+
+/// Reads the files of a document.
+pub trait Loader
+{
+    /// Returns the name of the file `name` refers to, where `from` is
+    /// the file containing the include directive, if any. The result is
+    /// used to load the file, to resolve its own includes and in errors.
+    fn resolve(&self, name: &str, from: Option<&str>) -> String;
+
+    /// Returns the content of a file returned by `resolve`.
+    fn load(&mut self, file: &str) -> io::Result<String>;
+}
+
+/// Loads in-memory files by their name.
+impl Loader for HashMap<String, String>
+{
+    fn resolve(&self, name: &str, _: Option<&str>) -> String
+    {
+        name.to_owned()
+    }
+
+    fn load(&mut self, file: &str) -> io::Result<String>
+    {
+        self.get(file)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No such file"))
+    }
+}
+
+/// Loads files from the file system. Included files are relative to
+/// the directory of the including file, and the first file to the
+/// root directory.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FsLoader
+{
+    root: PathBuf,
+}
+
+impl FsLoader
+{
+    /// Creates a loader resolving the first file in `root`.
+    pub fn new<P>(root: P) -> Self
+        where P: Into<PathBuf>
+    {
+        FsLoader { root: root.into() }
+    }
+}
+
+impl Loader for FsLoader
+{
+    fn resolve(&self, name: &str, from: Option<&str>) -> String
+    {
+        let dir = match from {
+            Some(from) => PathBuf::from(from).parent().map(PathBuf::from).unwrap_or_default(),
+            None => self.root.clone(),
+        };
+
+        dir.join(name).to_string_lossy().into_owned()
+    }
+
+    fn load(&mut self, file: &str) -> io::Result<String>
+    {
+        fs::read_to_string(file)
+    }
+}
+
+/// A document with all includes replaced, which maps positions
+/// back to the files they come from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Source
+{
+    text: String,
+    /// Where the text of each file starts and the position in that file.
+    spans: Vec<(usize, String, Position)>,
+}
+
+impl Source
+{
+    /// The text of the assembled document.
+    pub fn text(&self) -> &str
+    {
+        &self.text
+    }
+
+    /// Returns the file and the position in it of a position in `text`.
+    pub fn locate(&self, pos: Position) -> (&str, Position)
+    {
+        let offset = offset(&self.text, pos);
+        let &(start, ref file, mut file_pos) = self
+            .spans
+            .iter()
+            .rev()
+            .find(|&&(start, _, _)| start <= offset)
+            .expect("Bug: no span at the start");
+
+        file_pos.advance(&self.text.as_bytes()[start..offset]);
+
+        (file, file_pos)
+    }
+
+    /// Deserializes the assembled document.
+    pub fn deserialize<T>(&self) -> Result<T>
+        where T: DeserializeOwned
+    {
+        de::from_str(&self.text).map_err(|error| self.error(error))
+    }
+
+    /// Turns an error of parsing `text` into one in the right file.
+    pub fn error(&self, error: de::Error) -> Error
+    {
+        match error {
+            de::Error::Parser(kind, pos) if pos != (Position { line: 0, col: 0 }) => {
+                let (file, pos) = self.locate(pos);
+
+                Error::Parse { file: file.to_owned(), error: de::Error::Parser(kind, pos) }
+            }
+            error => Error::Parse { file: self.spans[0].1.clone(), error },
+        }
+    }
+}
+
+/// Loads `name` and replaces all includes.
+pub fn expand<L>(name: &str, loader: &mut L) -> Result<Source>
+    where L: Loader
+{
+    let file = loader.resolve(name, None);
+    let mut expander = Expander {
+        loader,
+        source: Source { text: String::new(), spans: Vec::new() },
+        stack: Vec::new(),
+    };
+
+    expander.file(file)?;
+
+    Ok(expander.source)
+}
+
+/// Loads and deserializes the document `name`.
+pub fn from_file<T, L>(name: &str, loader: &mut L) -> Result<T>
+    where T: DeserializeOwned, L: Loader
+{
+    expand(name, loader)?.deserialize()
+}
+
+/// The byte offset of `pos` in `text`.
+fn offset(text: &str, pos: Position) -> usize
+{
+    let mut current = Position { line: 1, col: 1 };
+
+    for (i, b) in text.bytes().enumerate() {
+        if (current.line, current.col) >= (pos.line, pos.col) {
+            return i;
+        }
+
+        current.advance(&[b]);
+    }
+
+    text.len()
+}
+
+struct Expander<'a, L: 'a>
+{
+    loader: &'a mut L,
+    source: Source,
+    /// The files being expanded, to detect cycles.
+    stack: Vec<String>,
+}
+
+impl<'a, L> Expander<'a, L>
+    where L: Loader
+{
+    fn file(&mut self, file: String) -> Result<()>
+    {
+        if self.stack.contains(&file) {
+            return Err(Error::Cycle(file));
+        }
+
+        let input = self.loader.load(&file).map_err(|e| Error::Load {
+            file: file.clone(),
+            message: e.to_string(),
+        })?;
+
+        let mut rest = &input[..];
+        let mut pos = Position { line: 1, col: 1 };
+
+        // Extensions apply to the whole document, so only the first file
+        // can enable them.
+        if !self.stack.is_empty() {
+            let bytes = Bytes::new(input.as_bytes());
+
+            if bytes.peek() == Some(b'#') {
+                let error = de::Error::Parser(ParseError::IncludedAttribute, bytes.position());
+
+                return Err(Error::Parse { file, error });
+            }
+        }
+
+        self.stack.push(file.clone());
+        self.source.spans.push((self.source.text.len(), file.clone(), pos));
+
+        loop {
+            let Include { start, end, name } = match find_include(rest) {
+                Ok(Some(include)) => include,
+                Ok(None) => break,
+                Err((kind, offset)) => {
+                    pos.advance(&rest.as_bytes()[..offset]);
+
+                    return Err(Error::Parse { file, error: de::Error::Parser(kind, pos) });
+                }
+            };
+
+            self.source.text += &rest[..start];
+            pos.advance(&rest.as_bytes()[..end]);
+            rest = &rest[end..];
+
+            let included = self.loader.resolve(&name, Some(&file));
+
+            self.file(included)?;
+            // Ends a line comment at the end of the included file.
+            self.source.text.push('\n');
+            self.source.spans.push((self.source.text.len(), file.clone(), pos));
+        }
+
+        self.source.text += rest;
+        self.stack.pop();
+
+        Ok(())
+    }
+}
+
+/// An include directive with its start and end offset.
+struct Include
+{
+    start: usize,
+    end: usize,
+    name: String,
+}
+
+/// The result of scanning for includes, with the offset of an error.
+type Scan<T> = ::std::result::Result<T, (ParseError, usize)>;
+
+/// Finds the next `include!("name")`, skipping strings and comments.
+fn find_include(input: &str) -> Scan<Option<Include>>
+{
+    let mut offset = 0;
+
+    while offset < input.len() {
+        let rest = &input[offset..];
+        let len = match next_token(rest) {
+            Ok((TokenKind::Ident, len)) if &rest[..len] == "include" && rest[len..].starts_with('!') => {
+                return directive(input, offset, len + 1).map(Some);
+            }
+            Ok((_, len)) => len,
+            // Let the parser report anything else.
+            Err(ParseError::UnexpectedByte(c)) => c.len_utf8(),
+            Err(_) => return Ok(None),
+        };
+
+        offset += len;
+    }
+
+    Ok(None)
+}
+
+/// Parses `("name")` after `include!`, starting at `start + len`.
+fn directive(input: &str, start: usize, len: usize) -> Scan<Include>
+{
+    let mut offset = start + len;
+    // Skips whitespace and comments, as the syntax tree allows them too.
+    let skip_ws = |mut offset: usize| {
+        while let Some(Ok((kind, len))) = input.get(offset..).filter(|s| !s.is_empty()).map(next_token) {
+            if !kind.is_trivia() {
+                break;
+            }
+
+            offset += len;
+        }
+
+        offset
+    };
+    let error = |offset: usize| Err((ParseError::ExpectedInclude, offset));
+
+    offset = skip_ws(offset);
+
+    if !input[offset..].starts_with('(') {
+        return error(offset);
+    }
+
+    offset = skip_ws(offset + 1);
+
+    let name = match next_token(&input[offset..]) {
+        Ok((TokenKind::String, len)) => {
+            let name = match Bytes::new(&input.as_bytes()[offset..offset + len]).string() {
+                Ok(ParsedStr::Allocated(s)) => s,
+                Ok(ParsedStr::Slice(s)) => s.to_owned(),
+                Err(_) => return error(offset),
+            };
+
+            offset = skip_ws(offset + len);
+
+            name
+        }
+        _ => return error(offset),
+    };
+
+    if !input[offset..].starts_with(')') {
+        return error(offset);
+    }
+
+    Ok(Include { start, end: offset + 1, name })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use value::Value;
+
+    fn files(files: &[(&str, &str)]) -> HashMap<String, String>
+    {
+        files.iter().map(|&(name, text)| (name.to_owned(), text.to_owned())).collect()
+    }
+
+    #[test]
+    fn test_expand()
+    {
+        let mut loader = files(&[
+            ("main", "[ include!( \"a\" ), \"include!(\\\"x\\\")\", /* include!(\"x\") */ include!(r\"b\") ]"),
+            ("a", "{ b: include!(\"b\") }"),
+            ("b", "// b\n(1, 2)"),
+        ]);
+        let source = expand("main", &mut loader).unwrap();
+
+        assert_eq!(
+            source.text(),
+            "[ { b: // b\n(1, 2)\n }\n, \"include!(\\\"x\\\")\", /* include!(\"x\") */ // b\n(1, 2)\n ]",
+        );
+        let locate = |line, col| {
+            let (file, pos) = source.locate(Position { line, col });
+
+            (file, pos.line, pos.col)
+        };
+
+        assert_eq!(locate(1, 1), ("main", 1, 1));
+        assert_eq!(locate(1, 3), ("a", 1, 1));
+        assert_eq!(locate(1, 5), ("a", 1, 3));
+        assert_eq!(locate(1, 8), ("b", 1, 1));
+        assert_eq!(locate(2, 2), ("b", 2, 2));
+        assert_eq!(locate(3, 2), ("a", 1, 20));
+        assert_eq!(locate(4, 1), ("main", 1, 18));
+        assert_eq!(locate(4, 41), ("main", 1, 58));
+        assert_eq!(locate(4, 42), ("b", 1, 1));
+        assert_eq!(locate(5, 1), ("b", 2, 1));
+        assert_eq!(locate(6, 1), ("main", 1, 73));
+        assert_eq!(source.deserialize::<Value>(), Ok(rson!([{ b: (1, 2) }, "include!(\"x\")", (1, 2)])));
+    }
+
+    #[test]
+    fn test_errors()
+    {
+        let mut loader = files(&[
+            ("main", "{\n    \"a\": include!(\"a\"),\n}"),
+            ("a", "include!(\"b\")"),
+            ("b", "[1, 2,\n x]"),
+            ("cycle", "[include!(\"a2\")]"),
+            ("a2", "include!(\"cycle\")"),
+            ("bad", "[\n  include!(b)]"),
+        ]);
+
+        match from_file::<HashMap<String, Vec<u8>>, _>("main", &mut loader) {
+            Err(Error::Parse { file, error: de::Error::Parser(_, pos) }) => {
+                assert_eq!((file.as_str(), pos), ("b", Position { line: 2, col: 2 }));
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        assert_eq!(expand("cycle", &mut loader), Err(Error::Cycle("cycle".to_owned())));
+        assert_eq!(
+            expand("bad", &mut loader),
+            Err(Error::Parse {
+                file: "bad".to_owned(),
+                error: de::Error::Parser(ParseError::ExpectedInclude, Position { line: 2, col: 12 }),
+            }),
+        );
+        assert_eq!(
+            expand("missing", &mut loader),
+            Err(Error::Load { file: "missing".to_owned(), message: "No such file".to_owned() }),
+        );
+    }
+
+    #[test]
+    fn test_included_comments_and_attributes()
+    {
+        let mut loader = files(&[
+            ("main", "{ a: include!(\"a\"), c: 2 }"),
+            ("a", "1 // one"),
+            ("attribute", "[include!(\"b\")]"),
+            ("b", "// b\n#![enable(implicit_some)]\n1"),
+        ]);
+
+        assert_eq!(from_file::<Value, _>("main", &mut loader), Ok(rson!({ "a": 1, "c": 2 })));
+        assert_eq!(
+            expand("attribute", &mut loader),
+            Err(Error::Parse {
+                file: "b".to_owned(),
+                error: de::Error::Parser(ParseError::IncludedAttribute, Position { line: 2, col: 1 }),
+            }),
+        );
+    }
+
+    #[test]
+    fn test_fs_loader()
+    {
+        let loader = FsLoader::new("levels");
+        let file = loader.resolve("scene.rson", None);
+
+        assert_eq!(PathBuf::from(&file), PathBuf::from("levels").join("scene.rson"));
+        assert_eq!(
+            PathBuf::from(loader.resolve("materials/metal.rson", Some(&file))),
+            PathBuf::from("levels").join("materials/metal.rson"),
+        );
+    }
+}
@@ -15,7 +15,24 @@ RSON is a simple config format which looks similar to Rust syntax.
 * Allows nested layout (similar to JSON)
 * Supports comments
 * Trailing commas
-* Pretty serialization
+* Streaming transcoding into any serde format (`transcode`)
+* Reading and writing RON files (`de::Options::ron`, `ser::Options::ron`, `ron` module)
+* Reading plain JSON with the same deserializer (`de::Options::json`)
+* Error messages with positions and suggestions for typos
+* Source positions of deserialized values (`de::Spanned`, `value::SpannedValue`)
+* Pretty serialization, optionally with comments above struct fields
+* Lossless syntax tree for tools which edit RSON files (`cst` module)
+* Format-preserving editing by path (`document` module)
+* Looking up values by path, with wildcards and filters (`Value::pointer`, `Value::query`)
+* Building values with RSON syntax (`rson!` macro)
+* Structural diffs which ignore formatting and comments (`diff` module)
+* Patches and deep merging of values (`patch` module)
+* Splitting documents into several files with `include!("file")` (`include` module)
+* Reusing values with `let name = value;` bindings (`de::Deserializer::bindings`)
+* Canonical formatting which keeps comments (`fmt` module, `rsonfmt` binary)
+* Keeping the order of map entries in `Value` and JSON objects (`preserve_order` feature)
+* Lossless conversion to and from JSON (`convert` module, `json` feature)
+* `rson` command-line tool for checking, converting and querying files (`cli` feature)
 
 ## Syntax example
 
@@ -60,13 +77,35 @@ Serializing / Deserializing is as simple as calling `to_string` / `from_str`.
 
 !*/
 
+#[cfg(feature = "preserve_order")]
+extern crate indexmap;
 extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
 #[cfg(test)]
 #[macro_use]
 extern crate serde_derive;
+#[cfg(all(test, not(feature = "json")))]
+extern crate serde_json;
 
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "json")]
+pub mod convert;
+pub mod cst;
 pub mod de;
+pub mod diff;
+pub mod document;
+pub mod fmt;
+pub mod include;
+pub mod patch;
+pub mod path;
+pub mod ron;
 pub mod ser;
 pub mod value;
 
 mod parse;
+mod transcode;
+
+pub use transcode::transcode;
@@ -0,0 +1,131 @@
+/// Builds a `value::Value` from RSON syntax with Rust expressions in it.
+///
+/// Struct names are dropped and fields become string keys, like when
+/// parsing a `Value`. Anything else is a Rust expression converted with
+/// `Value::from`, so bare identifiers are variables; enum variants are
+/// written as strings, and map keys which are variables in parentheses.
+///
+/// ```
+/// #[macro_use]
+/// extern crate rson_rs;
+///
+/// use rson_rs::value::Value;
+///
+/// # fn main() {
+/// let (w, h) = (10, 20);
+/// let key = "guy";
+///
+/// let value = rson!(Level {
+///     name: "hero",
+///     size: (w, h * 2),
+///     tags: ["a", 'b', None, Some(1.5), ()],
+///     characters: { (key): { friendly: true }, 3: "Yellow" },
+/// });
+///
+/// assert_eq!(value, Value::from_str("{
+///     name: \"hero\",
+///     size: (10, 40),
+///     tags: [\"a\", 'b', None, Some(1.5), ()],
+///     characters: { \"guy\": { friendly: true }, 3: \"Yellow\" },
+/// }").unwrap());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! rson {
+    // Elements of a sequence, collecting the tokens of the current one
+    // until a comma.
+    (@seq [$($elems:expr,)*] [$($cur:tt)+] , $($rest:tt)*) => {
+        $crate::rson!(@seq [$($elems,)* $crate::rson!($($cur)+),] [] $($rest)*)
+    };
+    (@seq [$($elems:expr,)*] [$($cur:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::rson!(@seq [$($elems,)*] [$($cur)* $next] $($rest)*)
+    };
+    (@seq [$($elems:expr,)*] [$($cur:tt)+]) => {
+        vec![$($elems,)* $crate::rson!($($cur)+)]
+    };
+    (@seq [$($elems:expr,)*] []) => {
+        vec![$($elems,)*]
+    };
+
+    // Keys of a map, collecting tokens until a colon.
+    (@map [$($entries:expr,)*] [$($key:tt)+] : $($rest:tt)*) => {
+        $crate::rson!(@value [$($entries,)*] [$($key)+] [] $($rest)*)
+    };
+    (@map [$($entries:expr,)*] [$($key:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::rson!(@map [$($entries,)*] [$($key)* $next] $($rest)*)
+    };
+    (@map [$($entries:expr,)*] []) => {
+        vec![$($entries,)*].into_iter().collect::<$crate::value::Map>()
+    };
+
+    // Values of a map, collecting tokens until a comma.
+    (@value [$($entries:expr,)*] [$($key:tt)+] [$($val:tt)+] , $($rest:tt)*) => {
+        $crate::rson!(@map [$($entries,)* ($crate::rson!(@key $($key)+), $crate::rson!($($val)+)),] [] $($rest)*)
+    };
+    (@value [$($entries:expr,)*] [$($key:tt)+] [$($val:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::rson!(@value [$($entries,)*] [$($key)+] [$($val)* $next] $($rest)*)
+    };
+    (@value [$($entries:expr,)*] [$($key:tt)+] [$($val:tt)+]) => {
+        $crate::rson!(@map [$($entries,)* ($crate::rson!(@key $($key)+), $crate::rson!($($val)+)),] [])
+    };
+
+    (@key $key:ident) => {
+        $crate::value::Value::String(stringify!($key).to_owned())
+    };
+    (@key $key:expr) => {
+        $crate::value::Value::from($key)
+    };
+
+    (None) => {
+        $crate::value::Value::Option(None)
+    };
+    (Some($($inner:tt)+)) => {
+        $crate::value::Value::Option(Some(Box::new($crate::rson!($($inner)+))))
+    };
+    (()) => {
+        $crate::value::Value::Unit
+    };
+    ([$($tt:tt)*]) => {
+        $crate::value::Value::Seq($crate::rson!(@seq [] [] $($tt)*))
+    };
+    (($($tt:tt)*)) => {
+        $crate::value::Value::Seq($crate::rson!(@seq [] [] $($tt)*))
+    };
+    ({$($tt:tt)*}) => {
+        $crate::value::Value::Map($crate::rson!(@map [] [] $($tt)*))
+    };
+    ($name:ident {$($tt:tt)*}) => {
+        $crate::value::Value::Map($crate::rson!(@map [] [] $($tt)*))
+    };
+    ($other:expr) => {
+        $crate::value::Value::from($other)
+    };
+}
+
+#[cfg(test)]
+mod tests
+{
+    use value::Value;
+
+    #[test]
+    fn test_rson()
+    {
+        let owner = Some("guy");
+        let sizes = vec![1, 2];
+
+        assert_eq!(rson!(()), Value::Unit);
+        assert_eq!(rson!([]), Value::Seq(vec![]));
+        assert_eq!(rson!({}), Value::Map(Default::default()));
+        assert_eq!(rson!(-1.5), Value::from(-1.5));
+        assert_eq!(rson!(owner), Value::from(Some("guy")));
+        assert_eq!(rson!(Some(Some(None))), "Some(Some(None))".parse().unwrap());
+        assert_eq!(
+            rson!([sizes.len(), sizes[0] + 1, (1, [2]), { "a": 1, 'b': [], }, Point { x: 1 },]),
+            "[2, 2, (1, [2]), { \"a\": 1, 'b': [] }, { x: 1 }]".parse().unwrap(),
+        );
+        assert_eq!(
+            rson!({ (sizes.len()): sizes.clone(), ("x".to_owned() + "y"): vec![1u8].len() }),
+            "{ 2: [1, 2], \"xy\": 1 }".parse().unwrap(),
+        );
+    }
+}
@@ -4,11 +4,19 @@ use std::str::{FromStr, from_utf8, from_utf8_unchecked};
 
 use de::{Error, ParseError, Result};
 
-const DIGITS: &[u8] = b"0123456789";
-const FLOAT_CHARS: &[u8] = b"0123456789.+-eE";
-const IDENT_FIRST: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz_";
-const IDENT_CHAR: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz_0123456789";
-const WHITE_SPACE: &[u8] = b"\n\t\r ";
+pub const DIGITS: &[u8] = b"0123456789";
+pub const FLOAT_CHARS: &[u8] = b"0123456789.+-eE";
+pub const IDENT_FIRST: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz_";
+pub const IDENT_CHAR: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz_0123456789";
+pub const WHITE_SPACE: &[u8] = b"\n\t\r ";
+
+/// Returns true if `s` can be written as a bare identifier.
+pub fn is_identifier(s: &str) -> bool
+{
+    let bytes = s.as_bytes();
+
+    !bytes.is_empty() && IDENT_FIRST.contains(&bytes[0]) && bytes.iter().all(|b| IDENT_CHAR.contains(b))
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct Bytes<'a>
@@ -78,17 +86,29 @@ impl<'a> Bytes<'a>
             return self.err(ParseError::ExpectedChar);
         }
 
-        let c = self.eat_byte()?;
-
-        let c = if c == b'\\' {
+        let c = if self.consume("\\") {
             let c = self.eat_byte()?;
 
             if c != b'\\' && c != b'\'' {
                 return self.err(ParseError::InvalidEscape);
             }
 
-            c
+            c as char
         } else {
+            // The length of the UTF-8 sequence follows from its first byte.
+            let len = match self.peek_or_eof()? {
+                0x00 ..= 0x7F => 1,
+                0xC0 ..= 0xDF => 2,
+                0xE0 ..= 0xEF => 3,
+                _ => 4,
+            };
+            let c = self.bytes.get(..len)
+                .and_then(|bytes| from_utf8(bytes).ok())
+                .and_then(|s| s.chars().next())
+                .ok_or_else(|| self.error(ParseError::ExpectedChar))?;
+
+            self.advance(len)?;
+
             c
         };
 
@@ -96,7 +116,7 @@ impl<'a> Bytes<'a>
             return self.err(ParseError::ExpectedChar);
         }
 
-        Ok(c as char)
+        Ok(c)
     }
 
     pub fn comma(&mut self) -> bool
@@ -163,7 +183,12 @@ impl<'a> Bytes<'a>
 
     pub fn error(&self, kind: ParseError) -> Error
     {
-        Error::Parser(kind, Position { line: self.line, col: self.column })
+        Error::Parser(kind, self.position())
+    }
+
+    pub fn position(&self) -> Position
+    {
+        Position { line: self.line, col: self.column }
     }
 
     pub fn float<T>(&mut self) -> Result<T>
@@ -179,7 +204,7 @@ impl<'a> Bytes<'a>
         res
     }
 
-    pub fn identifier(&mut self) -> Result<&[u8]>
+    pub fn identifier(&mut self) -> Result<&'a [u8]>
     {
         if IDENT_FIRST.contains(&self.peek_or_eof()?) {
             let bytes = self.next_bytes_contained_in(IDENT_CHAR);
@@ -381,6 +406,7 @@ impl<'a> Bytes<'a>
         match self.eat_byte()? {
             b'"' => store.push(b'"'),
             b'\\' => store.push(b'\\'),
+            b'/' => store.push(b'/'),
             b'b' => store.push(b'\x08'),
             b'f' => store.push(b'\x0c'),
             b'n' => store.push(b'\n'),
@@ -490,7 +516,9 @@ pub enum ParsedStr<'a>
     Slice(&'a str),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// A position in the input, with the line and the column counted
+/// in bytes, both starting at 1.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Position
 {
     pub col: usize,
@@ -504,3 +532,20 @@ impl Display for Position
         write!(f, "{}:{}", self.line, self.col)
     }
 }
+
+impl Position
+{
+    /// Moves the position past `bytes`, counting columns
+    /// the same way `Bytes` does.
+    pub fn advance(&mut self, bytes: &[u8])
+    {
+        for &b in bytes {
+            if b == b'\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,576 @@
+//! Patching and deep merging of values.
+//!
+//! A `Patch` is a list of operations, usually created from a `Diff`,
+//! which can be stored as RSON:
+//!
+//! ```
+//! use rson_rs::diff::diff;
+//! use rson_rs::patch::Patch;
+//! use rson_rs::ser::pretty;
+//! use rson_rs::value::Value;
+//!
+//! let old: Value = "{ buildings: [{ size: (10, 20) }], title: \"a\" }".parse().unwrap();
+//! let new: Value = "{ buildings: [{ size: (10, 30) }, { floor: 2 }] }".parse().unwrap();
+//!
+//! let patch = Patch::from(diff(&old, &new));
+//! let text = pretty::to_string(&patch).unwrap();
+//!
+//! assert_eq!(text, r#"[
+//!     {
+//!         op: Replace,
+//!         path: "buildings[0].size[1]",
+//!         value: 30,
+//!     },
+//!     {
+//!         op: Add,
+//!         path: "buildings[1]",
+//!         value: {
+//!             "floor": 2,
+//!         },
+//!     },
+//!     {
+//!         op: Remove,
+//!         path: "title",
+//!     },
+//! ]"#);
+//!
+//! let mut value = old.clone();
+//! value.apply_patch(&text.parse().unwrap()).unwrap();
+//!
+//! assert_eq!(value, new);
+//! ```
+//!
+//! `Merge` layers values on top of each other, like user settings over
+//! the defaults.
+
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use serde::de::Error as DeError;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use de;
+use diff::{Change, Diff};
+use path::{Path, Segment};
+use value::{key_segment, segment_key, Value};
+
+/// Patching result.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// The error returned when an operation of a patch can't be applied.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error
+{
+    /// There is no value at the path.
+    NotFound(Path),
+    /// The parent of the path isn't a map or a sequence
+    /// which the value can be added to.
+    CannotAdd(Path),
+}
+
+impl Display for Error
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        match *self {
+            Error::NotFound(ref path) => write!(f, "No value at `{}`", path),
+            Error::CannotAdd(ref path) => write!(f, "Can't add a value at `{}`", path),
+        }
+    }
+}
+
+impl StdError for Error
+{
+}
+
+/// A single change of a `Patch`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation
+{
+    /// Inserts a map entry or a sequence element, or replaces the root.
+    Add { path: Path, value: Value },
+    /// Removes a map entry or a sequence element.
+    Remove { path: Path },
+    /// Replaces an existing value.
+    Replace { path: Path, value: Value },
+}
+
+const OPERATIONS: &[&str] = &["Add", "Remove", "Replace"];
+
+/// The `op` field of an operation, written as a unit variant.
+struct OperationName(u32);
+
+impl Serialize for OperationName
+{
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.serialize_unit_variant("Operation", self.0, OPERATIONS[self.0 as usize])
+    }
+}
+
+impl Serialize for Operation
+{
+    /// Serializes the operation as `{ op: Add, path: "a.b", value: 1 }`.
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let (index, path, value) = match *self {
+            Operation::Add { ref path, ref value } => (0, path, Some(value)),
+            Operation::Remove { ref path } => (1, path, None),
+            Operation::Replace { ref path, ref value } => (2, path, Some(value)),
+        };
+
+        let mut s = serializer.serialize_struct("Operation", 2 + value.iter().count())?;
+        s.serialize_field("op", &OperationName(index))?;
+        s.serialize_field("path", path)?;
+
+        if let Some(value) = value {
+            s.serialize_field("value", value)?;
+        }
+
+        s.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Operation
+{
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        let mut operation = Value::deserialize(deserializer)?;
+        let path: Path = match operation.get("path").and_then(Value::as_str) {
+            Some(path) => path.parse().map_err(D::Error::custom)?,
+            None => return Err(D::Error::missing_field("path")),
+        };
+        let value = operation.get_mut("value").map(Value::take);
+
+        match (operation.get("op").and_then(Value::as_str), value) {
+            (Some("Add"), Some(value)) => Ok(Operation::Add { path, value }),
+            (Some("Remove"), _) => Ok(Operation::Remove { path }),
+            (Some("Replace"), Some(value)) => Ok(Operation::Replace { path, value }),
+            (Some("Add"), None) | (Some("Replace"), None) => Err(D::Error::missing_field("value")),
+            (Some(op), _) => Err(D::Error::unknown_variant(op, OPERATIONS)),
+            (None, _) => Err(D::Error::missing_field("op")),
+        }
+    }
+}
+
+/// Operations which are applied in order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Patch
+{
+    operations: Vec<Operation>,
+}
+
+impl Patch
+{
+    pub fn new() -> Self
+    {
+        Patch::default()
+    }
+
+    pub fn operations(&self) -> &[Operation]
+    {
+        &self.operations
+    }
+
+    pub fn push(&mut self, operation: Operation)
+    {
+        self.operations.push(operation);
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.operations.is_empty()
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.operations.len()
+    }
+}
+
+impl From<Vec<Operation>> for Patch
+{
+    fn from(operations: Vec<Operation>) -> Self
+    {
+        Patch { operations }
+    }
+}
+
+impl From<Diff> for Patch
+{
+    /// Creates a patch turning the old value of the diff into the new one.
+    fn from(diff: Diff) -> Self
+    {
+        let mut operations = Vec::new();
+        // The start and the parent of the last run of removed sequence
+        // elements, which are removed from the back to keep the indices.
+        let mut removed: Option<(usize, Path)> = None;
+
+        for change in diff {
+            let operation = match change {
+                Change::Added { path, value } => Operation::Add { path, value },
+                Change::Removed { path, .. } => Operation::Remove { path },
+                Change::Changed { path, new, .. } => Operation::Replace { path, value: new },
+            };
+
+            let parent = match operation {
+                Operation::Remove { ref path } => match path.split_last() {
+                    Some((parent, &Segment::Index(_))) => Some(parent),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            match (parent, removed.take()) {
+                (Some(parent), Some((start, last))) if parent == last => {
+                    operations.insert(start, operation);
+                    removed = Some((start, last));
+                }
+                (parent, _) => {
+                    removed = parent.map(|parent| (operations.len(), parent));
+                    operations.push(operation);
+                }
+            }
+        }
+
+        Patch { operations }
+    }
+}
+
+impl Serialize for Patch
+{
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        self.operations.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Patch
+{
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        Vec::deserialize(deserializer).map(Patch::from)
+    }
+}
+
+impl FromStr for Patch
+{
+    type Err = de::Error;
+
+    fn from_str(s: &str) -> de::Result<Self>
+    {
+        de::from_str(s)
+    }
+}
+
+impl Value
+{
+    /// Applies the operations of `patch` in order. If an operation
+    /// fails, the ones before it stay applied.
+    pub fn apply_patch(&mut self, patch: &Patch) -> Result<()>
+    {
+        for operation in &patch.operations {
+            apply(self, operation)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the value at `path`, looking through `Some`.
+fn container<'v>(root: &'v mut Value, path: &Path) -> Result<&'v mut Value>
+{
+    fn unwrap_some(value: &mut Value) -> &mut Value
+    {
+        match *value {
+            Value::Option(Some(ref mut inner)) => unwrap_some(inner),
+            ref mut value => value,
+        }
+    }
+
+    match root.get_path_mut(path) {
+        Some(value) => Ok(unwrap_some(value)),
+        None => Err(Error::NotFound(path.clone())),
+    }
+}
+
+fn apply(root: &mut Value, operation: &Operation) -> Result<()>
+{
+    match *operation {
+        Operation::Add { ref path, ref value } => {
+            let (parent, last) = match path.split_last() {
+                Some(split) => split,
+                None => {
+                    *root = value.clone();
+
+                    return Ok(());
+                }
+            };
+
+            match (container(root, &parent)?, last) {
+                (Value::Map(map), _) => {
+                    map.insert(segment_key(last), value.clone());
+                }
+                (Value::Seq(items), &Segment::Index(i)) if i <= items.len() => items.insert(i, value.clone()),
+                _ => return Err(Error::CannotAdd(path.clone())),
+            }
+        }
+        Operation::Remove { ref path } => {
+            let removed = match path.split_last() {
+                Some((parent, last)) => match (container(root, &parent)?, last) {
+                    (Value::Map(map), _) => map.remove(&segment_key(last)).is_some(),
+                    (Value::Seq(items), &Segment::Index(i)) if i < items.len() => {
+                        items.remove(i);
+
+                        true
+                    }
+                    _ => false,
+                },
+                None => false,
+            };
+
+            if !removed {
+                return Err(Error::NotFound(path.clone()));
+            }
+        }
+        Operation::Replace { ref path, ref value } => match root.get_path_mut(path) {
+            Some(old) => *old = value.clone(),
+            None => return Err(Error::NotFound(path.clone())),
+        },
+    }
+
+    Ok(())
+}
+
+/// How `Merge` combines two sequences.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum SeqStrategy
+{
+    /// The overlay replaces the base.
+    #[default]
+    Replace,
+    /// The elements of the overlay are appended to the base.
+    Append,
+    /// Elements of the overlay are merged into the element of the base
+    /// with the same value for the given key, or appended if there is none.
+    MergeByKey(String),
+}
+
+/// Deep merging of values.
+///
+/// Maps are merged entry by entry and `Some` values are merged with
+/// their content. Sequences are combined according to a `SeqStrategy`,
+/// which can be set for the sequences at specific paths. Anything else
+/// is replaced by the overlay.
+///
+/// ```
+/// use rson_rs::patch::{Merge, SeqStrategy};
+/// use rson_rs::value::Value;
+///
+/// let defaults: Value = "{ volume: 0.5, plugins: [\"a\"], keys: [{ action: Jump, key: Space }] }".parse().unwrap();
+/// let user: Value = "{ plugins: [\"b\"], keys: [{ action: Jump, key: W }] }".parse().unwrap();
+///
+/// let merged = Merge::new()
+///     .seqs(SeqStrategy::Append)
+///     .seqs_at("keys".parse().unwrap(), SeqStrategy::MergeByKey("action".to_owned()))
+///     .merge(defaults, user);
+///
+/// assert_eq!(merged, "{ volume: 0.5, plugins: [\"a\", \"b\"], keys: [{ action: Jump, key: W }] }".parse().unwrap());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Merge
+{
+    seqs: SeqStrategy,
+    paths: Vec<(Path, SeqStrategy)>,
+}
+
+impl Merge
+{
+    /// Creates a merge which replaces sequences.
+    pub fn new() -> Self
+    {
+        Merge::default()
+    }
+
+    /// Sets the strategy for all sequences without one for their path.
+    pub fn seqs(mut self, strategy: SeqStrategy) -> Self
+    {
+        self.seqs = strategy;
+        self
+    }
+
+    /// Sets the strategy for the sequence at `path` in the base value.
+    pub fn seqs_at(mut self, path: Path, strategy: SeqStrategy) -> Self
+    {
+        self.paths.push((path, strategy));
+        self
+    }
+
+    /// Merges `overlay` into `base`.
+    pub fn merge(&self, mut base: Value, overlay: Value) -> Value
+    {
+        self.merge_into(Some(Path::new()), &mut base, overlay);
+
+        base
+    }
+
+    fn strategy(&self, path: Option<&Path>) -> &SeqStrategy
+    {
+        self.paths
+            .iter()
+            .rev()
+            .find(|&(p, _)| Some(p) == path)
+            .map_or(&self.seqs, |(_, strategy)| strategy)
+    }
+
+    /// Merges recursively, where `path` is `None` below map keys
+    /// which can't be written in a path.
+    fn merge_into(&self, path: Option<Path>, base: &mut Value, overlay: Value)
+    {
+        match (base, overlay) {
+            (&mut Value::Map(ref mut map), Value::Map(overlay)) => {
+                for (key, value) in overlay {
+                    let path = path.as_ref().and_then(|p| key_segment(&key).map(|s| p.join(s)));
+
+                    match map.get_mut(&key) {
+                        Some(old) => self.merge_into(path, old, value),
+                        None => {
+                            map.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (&mut Value::Option(Some(ref mut base)), Value::Option(Some(overlay))) => {
+                self.merge_into(path, base, *overlay)
+            }
+            (&mut Value::Seq(ref mut items), Value::Seq(overlay)) => match *self.strategy(path.as_ref()) {
+                SeqStrategy::Replace => *items = overlay,
+                SeqStrategy::Append => items.extend(overlay),
+                SeqStrategy::MergeByKey(ref key) => {
+                    for item in overlay {
+                        let found = item
+                            .get(key)
+                            .and_then(|id| items.iter().position(|old| old.get(key) == Some(id)));
+
+                        match found {
+                            Some(i) => {
+                                let path = path.as_ref().map(|p| p.join(Segment::Index(i)));
+
+                                self.merge_into(path, &mut items[i], item);
+                            }
+                            None => items.push(item),
+                        }
+                    }
+                }
+            },
+            (base, overlay) => *base = overlay,
+        }
+    }
+}
+
+/// Merges `overlay` into `base`, replacing sequences.
+pub fn merge(base: Value, overlay: Value) -> Value
+{
+    Merge::new().merge(base, overlay)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use diff::diff;
+
+    fn path(s: &str) -> Path
+    {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_from_diff()
+    {
+        let old = rson!({ a: [1, 2, 3, 4], b: Some({ c: 1 }), d: { 1: 'x', 2: 'y', 3: 'z' } });
+        let new = rson!({ a: [0, 2], b: Some({ e: [] }), d: { 2: 'y' }, f: None });
+        let patch = Patch::from(diff(&old, &new));
+
+        assert_eq!(patch.operations(), &[
+            Operation::Replace { path: path("a[0]"), value: rson!(0) },
+            Operation::Remove { path: path("a[3]") },
+            Operation::Remove { path: path("a[2]") },
+            Operation::Remove { path: path("b.c") },
+            Operation::Add { path: path("b.e"), value: rson!([]) },
+            Operation::Remove { path: path("d[3]") },
+            Operation::Remove { path: path("d[1]") },
+            Operation::Add { path: path("f"), value: rson!(None) },
+        ]);
+
+        let mut value = old.clone();
+        value.apply_patch(&patch).unwrap();
+
+        assert_eq!(value, new);
+    }
+
+    #[test]
+    fn test_apply()
+    {
+        let mut value = rson!({ a: [1], b: "x" });
+        let apply = |value: &mut Value, s: &str| value.apply_patch(&s.parse().unwrap());
+
+        assert_eq!(apply(&mut value, "[{ op: Add, path: \"a[0]\", value: 0 }, { op: Add, path: \"c\", value: () }]"), Ok(()));
+        assert_eq!(value, rson!({ a: [0, 1], b: "x", c: () }));
+
+        assert_eq!(apply(&mut value, "[{ op: Add, path: \"a[3]\", value: 0 }]"), Err(Error::CannotAdd(path("a[3]"))));
+        assert_eq!(apply(&mut value, "[{ op: Add, path: \"b.c\", value: 0 }]"), Err(Error::CannotAdd(path("b.c"))));
+        assert_eq!(apply(&mut value, "[{ op: Remove, path: \"a[2]\" }]"), Err(Error::NotFound(path("a[2]"))));
+        assert_eq!(apply(&mut value, "[{ op: Replace, path: \"x.y\", value: 1 }]"), Err(Error::NotFound(path("x.y"))));
+        assert_eq!(apply(&mut value, "[{ op: Remove, path: \"\" }]"), Err(Error::NotFound(path(""))));
+        assert_eq!(apply(&mut value, "[{ op: Add, path: \"\", value: 1 }]"), Ok(()));
+        assert_eq!(value, rson!(1));
+
+        assert!("[{ op: Move, path: \"a\" }]".parse::<Patch>().is_err());
+        assert!("[{ op: Add, path: \"a\" }]".parse::<Patch>().is_err());
+        assert!("[{ op: Remove, path: \"a..b\" }]".parse::<Patch>().is_err());
+    }
+
+    #[test]
+    fn test_merge()
+    {
+        let base = rson!({
+            a: { b: 1, c: [1, 2] },
+            d: Some({ e: 1 }),
+            items: [{ id: 1, x: 1 }, { id: 2, x: 2 }, { x: 3 }],
+            (rson!((1, 2))): { f: [1] },
+        });
+        let overlay = rson!({
+            a: { c: [3], g: 1 },
+            d: Some({ h: 2 }),
+            items: [{ id: 2, x: 4 }, { id: 3 }, { x: 5 }],
+            (rson!((1, 2))): { f: [2] },
+        });
+
+        assert_eq!(merge(base.clone(), overlay.clone()), rson!({
+            a: { b: 1, c: [3], g: 1 },
+            d: Some({ e: 1, h: 2 }),
+            items: [{ id: 2, x: 4 }, { id: 3 }, { x: 5 }],
+            (rson!((1, 2))): { f: [2] },
+        }));
+        assert_eq!(
+            Merge::new()
+                .seqs(SeqStrategy::Append)
+                .seqs_at(path("items"), SeqStrategy::MergeByKey("id".to_owned()))
+                .merge(base, overlay),
+            rson!({
+                a: { b: 1, c: [1, 2, 3], g: 1 },
+                d: Some({ e: 1, h: 2 }),
+                items: [{ id: 1, x: 1 }, { id: 2, x: 4 }, { x: 3 }, { id: 3 }, { x: 5 }],
+                (rson!((1, 2))): { f: [1, 2] },
+            }),
+        );
+    }
+}
@@ -0,0 +1,552 @@
+//! Paths pointing into RSON values, like `level.buildings[0].color`.
+//!
+//! A path is a list of segments separated by dots or written in brackets:
+//!
+//! * `name` or `["name"]` selects a struct field or a map entry,
+//! * `0` or `[0]` selects an element of a sequence or a tuple.
+//!
+//! The empty path points at the root value.
+//!
+//! A `Query` additionally accepts steps selecting several values:
+//!
+//! * `*` or `[*]` selects all elements or map values,
+//! * `[path == value]` and `[path != value]` select the elements whose
+//!   value at the relative `path` compares as given, like
+//!   `buildings[owner == Some("guy")]`.
+
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult, Write};
+use std::str::FromStr;
+
+use serde::de::Error as DeError;
+use serde::{Deserialize, Deserializer as SerdeDeserializer, Serialize, Serializer};
+
+use de::Deserializer;
+use parse::{is_identifier, DIGITS, IDENT_CHAR};
+use value::Value;
+
+/// A single step of a `Path`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Segment
+{
+    /// A struct field or a map key.
+    Field(String),
+    /// An element of a sequence or a tuple.
+    Index(usize),
+}
+
+impl Display for Segment
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        match *self {
+            Segment::Field(ref name) if is_identifier(name) => write!(f, ".{}", name),
+            Segment::Field(ref name) => {
+                f.write_str("[\"")?;
+                for c in name.chars() {
+                    if c == '\\' || c == '"' {
+                        f.write_char('\\')?;
+                    }
+                    f.write_char(c)?;
+                }
+                f.write_str("\"]")
+            }
+            Segment::Index(i) => write!(f, "[{}]", i),
+        }
+    }
+}
+
+/// A parsed path.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Path
+{
+    segments: Vec<Segment>,
+}
+
+impl Path
+{
+    /// Creates the empty path, pointing at the root.
+    pub fn new() -> Self
+    {
+        Path::default()
+    }
+
+    pub fn segments(&self) -> &[Segment]
+    {
+        &self.segments
+    }
+
+    pub fn is_root(&self) -> bool
+    {
+        self.segments.is_empty()
+    }
+
+    pub fn push(&mut self, segment: Segment)
+    {
+        self.segments.push(segment);
+    }
+
+    pub fn pop(&mut self) -> Option<Segment>
+    {
+        self.segments.pop()
+    }
+
+    /// Returns a new path with `segment` appended.
+    pub fn join(&self, segment: Segment) -> Path
+    {
+        let mut path = self.clone();
+        path.push(segment);
+
+        path
+    }
+
+    /// Splits the path into its parent and the last segment.
+    pub fn split_last(&self) -> Option<(Path, &Segment)>
+    {
+        self.segments.split_last().map(|(last, parent)| {
+            (Path { segments: parent.to_vec() }, last)
+        })
+    }
+}
+
+impl From<Vec<Segment>> for Path
+{
+    fn from(segments: Vec<Segment>) -> Self
+    {
+        Path { segments }
+    }
+}
+
+impl Display for Path
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match *segment {
+                Segment::Field(ref name) if i == 0 && is_identifier(name) => f.write_str(name)?,
+                ref segment => segment.fmt(f)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Path
+{
+    type Err = ParsePathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        let mut parser = Parser { input: s, offset: 0, query: false };
+
+        parser.path(b"")
+    }
+}
+
+/// A step of a `Query`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Step
+{
+    /// Selects a single child, like in a `Path`.
+    Segment(Segment),
+    /// Selects all children.
+    Wildcard,
+    /// Selects the children whose value at the path compares to the value.
+    Filter(Path, Comparison, Value),
+}
+
+/// The comparison of a `Step::Filter`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Comparison
+{
+    Equal,
+    NotEqual,
+}
+
+impl Display for Step
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        match *self {
+            Step::Segment(ref segment) => segment.fmt(f),
+            Step::Wildcard => f.write_str("[*]"),
+            Step::Filter(ref path, comparison, ref value) => {
+                let op = match comparison {
+                    Comparison::Equal => "==",
+                    Comparison::NotEqual => "!=",
+                };
+                let value = ::ser::to_string(value).map_err(|_| ::std::fmt::Error)?;
+
+                write!(f, "[{} {} {}]", path, op, value)
+            }
+        }
+    }
+}
+
+/// A parsed query, a path which may contain wildcards and filters.
+///
+/// ```
+/// use rson_rs::path::Query;
+///
+/// let query: Query = "buildings[owner != None].size.0".parse().unwrap();
+///
+/// assert_eq!(query.steps().len(), 4);
+/// assert!("buildings[owner = 1]".parse::<Query>().is_err());
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Query
+{
+    steps: Vec<Step>,
+}
+
+impl Query
+{
+    pub fn steps(&self) -> &[Step]
+    {
+        &self.steps
+    }
+}
+
+impl From<Path> for Query
+{
+    fn from(path: Path) -> Self
+    {
+        Query { steps: path.segments.into_iter().map(Step::Segment).collect() }
+    }
+}
+
+impl Display for Query
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        for (i, step) in self.steps.iter().enumerate() {
+            match *step {
+                Step::Segment(Segment::Field(ref name)) if i == 0 && is_identifier(name) => f.write_str(name)?,
+                Step::Wildcard if i == 0 => f.write_str("*")?,
+                ref step => step.fmt(f)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Query
+{
+    type Err = ParsePathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        let mut parser = Parser { input: s, offset: 0, query: true };
+
+        Ok(Query { steps: parser.steps(b"")? })
+    }
+}
+
+impl Serialize for Path
+{
+    /// Serializes the path as a string.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Path
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: SerdeDeserializer<'de>
+    {
+        String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+    }
+}
+
+/// The error returned when a path can't be parsed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParsePathError
+{
+    /// The byte offset at which parsing failed.
+    pub offset: usize,
+}
+
+impl Display for ParsePathError
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult
+    {
+        write!(f, "Invalid path at offset {}", self.offset)
+    }
+}
+
+impl StdError for ParsePathError
+{
+}
+
+struct Parser<'a>
+{
+    input: &'a str,
+    offset: usize,
+    /// Whether wildcards and filters are allowed.
+    query: bool,
+}
+
+impl<'a> Parser<'a>
+{
+    fn rest(&self) -> &'a [u8]
+    {
+        &self.input.as_bytes()[self.offset..]
+    }
+
+    fn err<T>(&self) -> Result<T, ParsePathError>
+    {
+        Err(ParsePathError { offset: self.offset })
+    }
+
+    fn consume(&mut self, b: u8) -> bool
+    {
+        if self.rest().first() == Some(&b) {
+            self.offset += 1;
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_spaces(&mut self)
+    {
+        self.offset += self.rest().iter().take_while(|&&b| b == b' ').count();
+    }
+
+    fn path(&mut self, end: &[u8]) -> Result<Path, ParsePathError>
+    {
+        let steps = self.steps(end)?;
+        let segments = steps.into_iter().map(|step| match step {
+            Step::Segment(segment) => segment,
+            _ => unreachable!("Bug: query step in a path"),
+        });
+
+        Ok(Path { segments: segments.collect() })
+    }
+
+    /// Parses steps until the end of the input or one of the `end` bytes.
+    fn steps(&mut self, end: &[u8]) -> Result<Vec<Step>, ParsePathError>
+    {
+        let mut steps = Vec::new();
+        let at_end = |p: &Self| p.rest().first().filter(|b| !end.contains(b)).is_none();
+
+        if at_end(self) {
+            return Ok(steps);
+        }
+
+        if self.rest()[0] != b'[' {
+            steps.push(self.dotted()?);
+        }
+
+        while !at_end(self) {
+            if self.consume(b'.') {
+                steps.push(self.dotted()?);
+            } else if self.consume(b'[') {
+                steps.push(self.bracketed()?);
+            } else {
+                return self.err();
+            }
+        }
+
+        Ok(steps)
+    }
+
+    /// Parses a field name or an index written after a dot.
+    fn dotted(&mut self) -> Result<Step, ParsePathError>
+    {
+        if self.query && self.consume(b'*') {
+            return Ok(Step::Wildcard);
+        }
+
+        let len = self.rest().iter().take_while(|b| IDENT_CHAR.contains(b)).count();
+
+        if len == 0 {
+            return self.err();
+        }
+
+        let word = &self.input[self.offset..self.offset + len];
+        let segment = if word.bytes().all(|b| DIGITS.contains(&b)) {
+            match word.parse() {
+                Ok(i) => Segment::Index(i),
+                Err(_) => return self.err(),
+            }
+        } else {
+            Segment::Field(word.to_owned())
+        };
+
+        self.offset += len;
+
+        Ok(Step::Segment(segment))
+    }
+
+    /// Parses the inside of `[..]`, after the opening bracket.
+    fn bracketed(&mut self) -> Result<Step, ParsePathError>
+    {
+        let step = if self.query && self.consume(b'*') {
+            Step::Wildcard
+        } else if self.query && !matches!(self.rest().first(), Some(b'"') | Some(b'0'..=b'9')) {
+            self.filter()?
+        } else if self.consume(b'"') {
+            Step::Segment(Segment::Field(self.string()?))
+        } else {
+            let len = self.rest().iter().take_while(|b| DIGITS.contains(b)).count();
+
+            match self.input[self.offset..self.offset + len].parse() {
+                Ok(i) => {
+                    self.offset += len;
+
+                    Step::Segment(Segment::Index(i))
+                }
+                Err(_) => return self.err(),
+            }
+        };
+
+        if self.consume(b']') {
+            Ok(step)
+        } else {
+            self.err()
+        }
+    }
+
+    /// Parses `path == value` or `path != value`, up to the closing bracket.
+    fn filter(&mut self) -> Result<Step, ParsePathError>
+    {
+        self.skip_spaces();
+        self.query = false;
+
+        let path = self.path(b" =!]")?;
+
+        self.query = true;
+
+        self.skip_spaces();
+
+        let comparison = if self.rest().starts_with(b"==") {
+            Comparison::Equal
+        } else if self.rest().starts_with(b"!=") {
+            Comparison::NotEqual
+        } else {
+            return self.err();
+        };
+
+        self.offset += 2;
+        self.skip_spaces();
+
+        let rest = &self.input[self.offset..];
+        let mut deserializer = Deserializer::from_str(rest);
+        let value = match Value::deserialize(&mut deserializer) {
+            Ok(value) => value,
+            Err(_) => return self.err(),
+        };
+
+        self.offset += rest.len() - deserializer.remainder().len();
+        self.skip_spaces();
+
+        Ok(Step::Filter(path, comparison, value))
+    }
+
+    /// Parses a string after the opening quote,
+    /// where only `\"` and `\\` are escapes.
+    fn string(&mut self) -> Result<String, ParsePathError>
+    {
+        let mut s = String::new();
+        let mut chars = self.input[self.offset..].char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.offset += i + 1;
+
+                    return Ok(s);
+                }
+                '\\' => match chars.next() {
+                    Some((_, c)) if c == '"' || c == '\\' => s.push(c),
+                    _ => {
+                        self.offset += i;
+
+                        return self.err();
+                    }
+                },
+                c => s.push(c),
+            }
+        }
+
+        self.offset = self.input.len();
+
+        self.err()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn path(s: &str) -> Path
+    {
+        s.parse().expect("Failed to parse path")
+    }
+
+    #[test]
+    fn test_parse()
+    {
+        use self::Segment::*;
+
+        assert!(path("").is_root());
+        assert_eq!(path("level.buildings[1].size.0").segments(), &[
+            Field("level".to_owned()),
+            Field("buildings".to_owned()),
+            Index(1),
+            Field("size".to_owned()),
+            Index(0),
+        ]);
+        assert_eq!(path("[2][\"a \\\"b\\\"\"]").segments(), &[
+            Index(2),
+            Field("a \"b\"".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn test_display()
+    {
+        assert_eq!(path("level.buildings.1").to_string(), "level.buildings[1]");
+        assert_eq!(path("[\"the key\"].x").to_string(), "[\"the key\"].x");
+    }
+
+    #[test]
+    fn test_errors()
+    {
+        assert_eq!("a..b".parse::<Path>(), Err(ParsePathError { offset: 2 }));
+        assert_eq!("a[x]".parse::<Path>(), Err(ParsePathError { offset: 2 }));
+        assert_eq!("a[\"b".parse::<Path>(), Err(ParsePathError { offset: 4 }));
+        assert_eq!("a b".parse::<Path>(), Err(ParsePathError { offset: 1 }));
+        assert_eq!("a[*]".parse::<Path>(), Err(ParsePathError { offset: 2 }));
+    }
+
+    #[test]
+    fn test_query()
+    {
+        let query: Query = "*.buildings[*][owner.name != Some(\"a]\")][\"x\"].1".parse().unwrap();
+
+        assert_eq!(query.steps()[0], Step::Wildcard);
+        assert_eq!(query.steps()[2], Step::Wildcard);
+        assert_eq!(query.steps()[3], Step::Filter(
+            path("owner.name"),
+            Comparison::NotEqual,
+            Value::from_str("Some(\"a]\")").unwrap(),
+        ));
+        assert_eq!(query.to_string(), "*.buildings[*][owner.name != Some(\"a]\")].x[1]");
+        assert_eq!(Query::from(path("a[0]")), "a.0".parse().unwrap());
+
+        assert_eq!("a[b = 1]".parse::<Query>(), Err(ParsePathError { offset: 4 }));
+        assert_eq!("a[b == ]".parse::<Query>(), Err(ParsePathError { offset: 7 }));
+        assert_eq!("a[b == 1".parse::<Query>(), Err(ParsePathError { offset: 8 }));
+        assert_eq!("a[*.b == 1]".parse::<Query>(), Err(ParsePathError { offset: 3 }));
+        assert_eq!("a[b.* == 1]".parse::<Query>(), Err(ParsePathError { offset: 4 }));
+    }
+}
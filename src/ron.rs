@@ -0,0 +1,180 @@
+//! Converting files between RSON and RON.
+//!
+//! The two formats only differ in how structs are written: RSON uses
+//! `Name { field: value }` where RON uses `Name(field: value)`. The
+//! conversion rewrites just the brackets of structs, so comments and
+//! layout are kept.
+//!
+//! Empty RON structs are ambiguous, as `Name()` can also be an empty
+//! tuple struct, so they are left as they are when converting to RSON.
+//!
+//! ```
+//! use rson_rs::ron::{ron_to_rson, rson_to_ron};
+//!
+//! let rson = "Sprite {\n    size: (1, 2), // pixels\n    tags: { \"a\": 1 },\n}";
+//! let ron = "Sprite(\n    size: (1, 2), // pixels\n    tags: { \"a\": 1 },\n)";
+//!
+//! assert_eq!(rson_to_ron(rson).unwrap(), ron);
+//! assert_eq!(ron_to_rson(ron).unwrap(), rson);
+//! ```
+
+use cst::lexer::tokenize;
+use cst::{Token, TokenKind};
+use de::{Deserializer, Error, Options, Result};
+
+/// Converts RSON text to RON.
+pub fn rson_to_ron(input: &str) -> Result<String>
+{
+    let tokens = tokens(input)?;
+
+    Ok(convert(&tokens, TokenKind::OpenBrace, TokenKind::CloseBrace, "(", ")"))
+}
+
+/// Converts RON text to RSON.
+///
+/// Fails if the input enables RON extensions, which have no
+/// equivalent in RSON.
+pub fn ron_to_rson(input: &str) -> Result<String>
+{
+    let input = strip_attributes(input)?;
+    let tokens = tokens(&input)?;
+
+    Ok(convert(&tokens, TokenKind::OpenParen, TokenKind::CloseParen, " {", "}"))
+}
+
+fn tokens(input: &str) -> Result<Vec<Token>>
+{
+    Ok(tokenize(input)?.into_iter().map(|(token, _)| token).collect())
+}
+
+/// Removes `#![enable()]` attributes and the whitespace after them.
+fn strip_attributes(input: &str) -> Result<String>
+{
+    if *Deserializer::with_options(input, Options::ron())?.options() != Options::ron() {
+        return Err(Error::Message("RON extensions can't be converted to RSON".to_owned()));
+    }
+
+    let mut out = String::new();
+    let mut rest = input;
+
+    loop {
+        let mut deserializer = Deserializer::from_str(rest);
+        let start = rest.len() - deserializer.remainder().len();
+
+        out += &rest[..start];
+        rest = &rest[start..];
+
+        if !deserializer.extension()? {
+            break;
+        }
+
+        let end = rest.len() - deserializer.remainder().len();
+        rest = rest[end..].trim_start();
+    }
+
+    out += rest;
+
+    Ok(out)
+}
+
+fn next_significant(tokens: &[Token]) -> Option<(usize, TokenKind)>
+{
+    tokens.iter().enumerate().find(|&(_, t)| !t.kind().is_trivia()).map(|(i, t)| (i, t.kind()))
+}
+
+/// Checks if the tokens after an opening bracket start with `field:`.
+fn starts_with_field(tokens: &[Token]) -> bool
+{
+    match next_significant(tokens) {
+        Some((i, TokenKind::Ident)) => {
+            matches!(next_significant(&tokens[i + 1..]), Some((_, TokenKind::Colon)))
+        }
+        _ => false,
+    }
+}
+
+/// Replaces the `open` and `close` brackets of structs.
+fn convert(tokens: &[Token], open: TokenKind, close: TokenKind, new_open: &str, new_close: &str) -> String
+{
+    let mut out = String::new();
+    let mut stack = Vec::new();
+    let mut last = None;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let kind = token.kind();
+
+        if kind == open {
+            let named = last == Some(TokenKind::Ident) && open == TokenKind::OpenBrace;
+            let is_struct = named || starts_with_field(&tokens[i + 1..]);
+
+            let is_empty = matches!(next_significant(&tokens[i + 1..]), Some((_, k)) if k == close);
+
+            if is_struct && (named || !is_empty) {
+                if named || last == Some(TokenKind::Ident) {
+                    let trimmed = out.trim_end().len();
+                    out.truncate(trimmed);
+                    out += new_open;
+                } else {
+                    out += new_open.trim_start();
+                }
+
+                stack.push(true);
+                last = Some(kind);
+
+                continue;
+            }
+
+            stack.push(false);
+        } else if matches!(kind, TokenKind::OpenBrace | TokenKind::OpenParen | TokenKind::OpenBracket) {
+            stack.push(false);
+        } else if matches!(kind, TokenKind::CloseBrace | TokenKind::CloseParen | TokenKind::CloseBracket)
+            && stack.pop() == Some(true)
+        {
+            out += new_close;
+            last = Some(kind);
+
+            continue;
+        }
+
+        out += token.text();
+
+        if !kind.is_trivia() {
+            last = Some(kind);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use de::from_str_with_options;
+
+    #[test]
+    fn test_rson_to_ron()
+    {
+        assert_eq!(
+            rson_to_ron("Level { /* size */ size: (1, 2), kind: Tile {x: {}}, map: {1: Some(A {b: [c]})}, empty: E {} }").unwrap(),
+            "Level( /* size */ size: (1, 2), kind: Tile(x: {}), map: {1: Some(A(b: [c]))}, empty: E() )",
+        );
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct E {}
+
+        assert_eq!(from_str_with_options(&rson_to_ron("E { }").unwrap(), Options::ron()), Ok(E {}));
+    }
+
+    #[test]
+    fn test_ron_to_rson()
+    {
+        assert_eq!(
+            ron_to_rson("#![enable()]\n// level\nLevel(size: (1, 2), kind: Tile(x: {}), tuple: T(a, b), e: E())").unwrap(),
+            "// level\nLevel {size: (1, 2), kind: Tile {x: {}}, tuple: T(a, b), e: E()}",
+        );
+        assert_eq!(ron_to_rson("#![enable(/* ] */)]\n#![enable()] /* ] */ 1").unwrap(), "/* ] */ 1");
+        assert!(ron_to_rson("#![enable(implicit_some)] 1").is_err());
+        assert!(ron_to_rson("#![enable(/* ] */ implicit_some)] 1").is_err());
+    }
+}
@@ -3,6 +3,8 @@ use std::fmt::{Display, Formatter, Result as FmtResult};
 
 use serde::ser::{self, Serialize};
 
+pub use de::Dialect;
+
 pub mod pretty;
 
 #[cfg(test)]
@@ -21,16 +23,54 @@ const NEWLINE: &str = "\r\n";
 /// if you want that, you can use `pretty::to_string` instead.
 pub fn to_string<T>(value: &T) -> Result<String>
     where T: Serialize
+{
+    to_string_with_options(value, Options::default())
+}
+
+/// Serializes `value` according to `options`, without any formatting.
+pub fn to_string_with_options<T>(value: &T, options: Options) -> Result<String>
+    where T: Serialize
 {
     let mut s = Serializer {
         output: String::new(),
         pretty: None,
-        struct_names: false,
+        options,
     };
     value.serialize(&mut s)?;
     Ok(s.output)
 }
 
+/// Options controlling the output of the serializer.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Options
+{
+    /// The syntax to write. With `Dialect::Ron`, structs are written
+    /// as `(field: value)` and strings use RON's escapes.
+    pub dialect: Dialect,
+    /// Write the names of structs, e.g. `Point { x: 1, y: 2 }`.
+    pub struct_names: bool,
+    /// Write `Some(value)` as just `value`, for reading with
+    /// `de::Options::implicit_some`. Values which would be ambiguous,
+    /// like the inner `None` of `Some(None)`, keep the wrapper.
+    pub implicit_some: bool,
+    /// Write newtype structs as just their content and newtype variants
+    /// holding a struct as `Variant { .. }`, for reading with
+    /// `de::Options::unwrap_newtypes`.
+    pub unwrap_newtypes: bool,
+}
+
+impl Options
+{
+    /// Options for writing RON files.
+    pub fn ron() -> Self
+    {
+        Options {
+            dialect: Dialect::Ron,
+            ..Options::default()
+        }
+    }
+}
+
 /// Serialization result.
 pub type Result<T> = ::std::result::Result<T, Error>;
 
@@ -69,23 +109,26 @@ impl StdError for Error
     }
 }
 
-struct Pretty
+struct Pretty<'c>
 {
     indent: usize,
+    comments: &'c pretty::Comments,
+    /// Names of the structs currently being serialized, innermost last.
+    structs: Vec<&'static str>,
 }
 
 /// The RSON serializer.
 ///
 /// You can just use `to_string` for deserializing a value.
 /// If you want it pretty-printed, take a look at the `pretty` module.
-pub struct Serializer
+pub struct Serializer<'c>
 {
     output: String,
-    pretty: Option<Pretty>,
-    struct_names: bool,
+    pretty: Option<Pretty<'c>>,
+    options: Options,
 }
 
-impl Serializer
+impl<'c> Serializer<'c>
 {
     fn start_indent(&mut self)
     {
@@ -109,9 +152,109 @@ impl Serializer
             self.output.extend((0..pretty.indent * 4).map(|_| " "));
         }
     }
+
+    fn start_struct(&mut self, name: &'static str)
+    {
+        if let Some(ref mut pretty) = self.pretty {
+            pretty.structs.push(name);
+        }
+    }
+
+    fn end_struct(&mut self)
+    {
+        if let Some(ref mut pretty) = self.pretty {
+            pretty.structs.pop();
+        }
+    }
+
+    fn open_struct(&mut self)
+    {
+        self.output += match self.options.dialect {
+            Dialect::Rson => "{",
+            Dialect::Ron => "(",
+        };
+    }
+
+    fn close_struct(&mut self)
+    {
+        self.output += match self.options.dialect {
+            Dialect::Rson => "}",
+            Dialect::Ron => ")",
+        };
+    }
+
+    /// Checks if `written` is an anonymous struct, which can follow
+    /// a variant name directly.
+    fn is_struct(&self, written: &str) -> bool
+    {
+        match self.options.dialect {
+            Dialect::Rson => written.starts_with('{'),
+            Dialect::Ron => match written.strip_prefix('(') {
+                Some(rest) => {
+                    let field = rest.trim_start();
+                    let len = field.bytes().take_while(|b| b.is_ascii_alphanumeric() || *b == b'_').count();
+
+                    len > 0 && field[len..].starts_with(':')
+                }
+                None => false,
+            },
+        }
+    }
+
+    /// Writes `c` as part of a string, escaped if needed.
+    fn string_char(&mut self, c: char)
+    {
+        if c == '\\' || c == '"' {
+            self.output.push('\\');
+            self.output.push(c);
+
+            return;
+        }
+
+        if self.options.dialect == Dialect::Ron && c.is_control() {
+            match c {
+                '\n' => self.output += "\\n",
+                '\r' => self.output += "\\r",
+                '\t' => self.output += "\\t",
+                c => self.output += &format!("\\u{:04x}", c as u32),
+            }
+
+            return;
+        }
+
+        self.output.push(c);
+    }
+
+    /// Writes the comment registered for `field` of the current struct.
+    fn field_comment(&mut self, field: &str)
+    {
+        let comment = match self.pretty {
+            Some(ref pretty) => {
+                let comments = pretty.comments;
+
+                pretty.structs.last().and_then(|name| comments.get(name, field))
+            }
+            None => None,
+        };
+
+        if let Some(comment) = comment {
+            for line in comment.lines() {
+                self.indent();
+
+                if line.is_empty() {
+                    self.output += "//";
+                } else {
+                    self.output += "// ";
+                    self.output += line;
+                }
+
+                self.output += NEWLINE;
+            }
+        }
+    }
 }
 
-impl<'a> ser::Serializer for &'a mut Serializer
+impl<'a, 'c> ser::Serializer for &'a mut Serializer<'c>
 {
     type Ok = ();
     type Error = Error;
@@ -199,10 +342,7 @@ impl<'a> ser::Serializer for &'a mut Serializer
     {
         self.output += "\"";
         for char in v.chars() {
-            if char == '\\' || char == '"' {
-                self.output.push('\\');
-            }
-            self.output.push(char);
+            self.string_char(char);
         }
         self.output += "\"";
         Ok(())
@@ -228,9 +368,23 @@ impl<'a> ser::Serializer for &'a mut Serializer
     fn serialize_some<T>(self, value: &T) -> Result<()>
         where T: ?Sized + Serialize
     {
-        self.output += "Some(";
+        if !self.options.implicit_some {
+            self.output += "Some(";
+            value.serialize(&mut *self)?;
+            self.output += ")";
+
+            return Ok(());
+        }
+
+        let start = self.output.len();
         value.serialize(&mut *self)?;
-        self.output += ")";
+
+        let written = &self.output[start..];
+
+        if written == "None" || written.starts_with("Some(") {
+            self.output.insert_str(start, "Some(");
+            self.output += ")";
+        }
 
         Ok(())
     }
@@ -244,7 +398,7 @@ impl<'a> ser::Serializer for &'a mut Serializer
 
     fn serialize_unit_struct(self, name: &'static str) -> Result<()>
     {
-        if self.struct_names {
+        if self.options.struct_names {
             self.output += name;
 
             Ok(())
@@ -268,7 +422,11 @@ impl<'a> ser::Serializer for &'a mut Serializer
     fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
         where T: ?Sized + Serialize
     {
-        if self.struct_names {
+        if self.options.unwrap_newtypes {
+            return value.serialize(self);
+        }
+
+        if self.options.struct_names {
             self.output += name;
         }
 
@@ -288,9 +446,15 @@ impl<'a> ser::Serializer for &'a mut Serializer
         where T: ?Sized + Serialize
     {
         self.output += variant;
-        self.output += "(";
+
+        let start = self.output.len();
         value.serialize(&mut *self)?;
-        self.output += ")";
+
+        if !(self.options.unwrap_newtypes && self.is_struct(&self.output[start..])) {
+            self.output.insert(start, '(');
+            self.output += ")";
+        }
+
         Ok(())
     }
 
@@ -316,7 +480,7 @@ impl<'a> ser::Serializer for &'a mut Serializer
         len: usize
     ) -> Result<Self::SerializeTupleStruct>
     {
-        if self.struct_names {
+        if self.options.struct_names {
             self.output += name;
         }
 
@@ -354,12 +518,13 @@ impl<'a> ser::Serializer for &'a mut Serializer
         _: usize
     ) -> Result<Self::SerializeStruct>
     {
-        if self.struct_names {
+        if self.options.struct_names {
             self.output += name;
         }
-        self.output += "{";
+        self.open_struct();
 
         self.start_indent();
+        self.start_struct(name);
 
         Ok(self)
     }
@@ -373,15 +538,16 @@ impl<'a> ser::Serializer for &'a mut Serializer
     ) -> Result<Self::SerializeStructVariant>
     {
         self.output += variant;
-        self.output += "{";
+        self.open_struct();
 
         self.start_indent();
+        self.start_struct(variant);
 
         Ok(self)
     }
 }
 
-impl<'a> ser::SerializeSeq for &'a mut Serializer
+impl<'a, 'c> ser::SerializeSeq for &'a mut Serializer<'c>
 {
     type Ok = ();
     type Error = Error;
@@ -410,7 +576,7 @@ impl<'a> ser::SerializeSeq for &'a mut Serializer
     }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut Serializer
+impl<'a, 'c> ser::SerializeTuple for &'a mut Serializer<'c>
 {
     type Ok = ();
     type Error = Error;
@@ -442,7 +608,7 @@ impl<'a> ser::SerializeTuple for &'a mut Serializer
 }
 
 // Same thing but for tuple structs.
-impl<'a> ser::SerializeTupleStruct for &'a mut Serializer
+impl<'a, 'c> ser::SerializeTupleStruct for &'a mut Serializer<'c>
 {
     type Ok = ();
     type Error = Error;
@@ -459,7 +625,7 @@ impl<'a> ser::SerializeTupleStruct for &'a mut Serializer
     }
 }
 
-impl<'a> ser::SerializeTupleVariant for &'a mut Serializer
+impl<'a, 'c> ser::SerializeTupleVariant for &'a mut Serializer<'c>
 {
     type Ok = ();
     type Error = Error;
@@ -476,7 +642,7 @@ impl<'a> ser::SerializeTupleVariant for &'a mut Serializer
     }
 }
 
-impl<'a> ser::SerializeMap for &'a mut Serializer
+impl<'a, 'c> ser::SerializeMap for &'a mut Serializer<'c>
 {
     type Ok = ();
     type Error = Error;
@@ -517,7 +683,7 @@ impl<'a> ser::SerializeMap for &'a mut Serializer
     }
 }
 
-impl<'a> ser::SerializeStruct for &'a mut Serializer
+impl<'a, 'c> ser::SerializeStruct for &'a mut Serializer<'c>
 {
     type Ok = ();
     type Error = Error;
@@ -525,6 +691,7 @@ impl<'a> ser::SerializeStruct for &'a mut Serializer
     fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
         where T: ?Sized + Serialize
     {
+        self.field_comment(key);
         self.indent();
 
         self.output += key;
@@ -547,13 +714,14 @@ impl<'a> ser::SerializeStruct for &'a mut Serializer
     fn end(self) -> Result<()>
     {
         self.end_indent();
+        self.end_struct();
 
-        self.output += "}";
+        self.close_struct();
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeStructVariant for &'a mut Serializer
+impl<'a, 'c> ser::SerializeStructVariant for &'a mut Serializer<'c>
 {
     type Ok = ();
     type Error = Error;
@@ -1,18 +1,109 @@
 //! Provides pretty serialization with `to_string`.
 
-use super::{Pretty, Result, Serializer};
+use std::collections::HashMap;
+
+use super::{Options, Pretty, Result, Serializer};
 
 use serde::ser::Serialize;
 
 /// Serializes `value` in the recommended RSON layout.
 pub fn to_string<T>(value: &T) -> Result<String>
     where T: Serialize
+{
+    to_string_with_comments(value, &Comments::new())
+}
+
+/// Serializes `value` in the recommended RSON layout,
+/// writing the matching `comments` above struct fields.
+///
+/// ```
+/// use rson_rs::ser::pretty::{to_string_with_comments, Comments};
+/// # #[macro_use] extern crate serde_derive;
+/// # extern crate rson_rs;
+///
+/// #[derive(Serialize)]
+/// struct Config { volume: f32 }
+///
+/// # fn main() {
+/// let comments = Comments::new().field("Config", "volume", "Between 0 and 1");
+///
+/// assert_eq!(
+///     to_string_with_comments(&Config { volume: 0.5 }, &comments).unwrap(),
+///     "{\n    // Between 0 and 1\n    volume: 0.5,\n}",
+/// );
+/// # }
+/// ```
+pub fn to_string_with_comments<T>(value: &T, comments: &Comments) -> Result<String>
+    where T: Serialize
+{
+    serialize(value, comments, Options::default())
+}
+
+/// Serializes `value` in the recommended layout, according to `options`.
+pub fn to_string_with_options<T>(value: &T, options: Options) -> Result<String>
+    where T: Serialize
+{
+    serialize(value, &Comments::new(), options)
+}
+
+fn serialize<T>(value: &T, comments: &Comments, options: Options) -> Result<String>
+    where T: Serialize
 {
     let mut s = Serializer {
         output: String::new(),
-        pretty: Some(Pretty { indent: 0 }),
-        struct_names: false,
+        pretty: Some(Pretty {
+            indent: 0,
+            comments,
+            structs: Vec::new(),
+        }),
+        options,
     };
     value.serialize(&mut s)?;
     Ok(s.output)
 }
+
+/// Doc comments for struct fields, keyed by the name of the struct
+/// (or struct variant) and the name of the field.
+///
+/// Every line of a comment is written as a separate `//` comment.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Comments
+{
+    /// Comments by struct name and field name.
+    fields: HashMap<String, HashMap<String, String>>,
+}
+
+impl Comments
+{
+    pub fn new() -> Self
+    {
+        Comments::default()
+    }
+
+    /// Adds the comment for `field` of the struct `struct_name`.
+    pub fn field(mut self, struct_name: &str, field: &str, comment: &str) -> Self
+    {
+        self.insert(struct_name, field, comment);
+
+        self
+    }
+
+    /// Adds the comment for `field` of the struct `struct_name`,
+    /// replacing any previous one.
+    pub fn insert(&mut self, struct_name: &str, field: &str, comment: &str)
+    {
+        self.fields
+            .entry(struct_name.to_owned())
+            .or_default()
+            .insert(field.to_owned(), comment.to_owned());
+    }
+
+    /// Returns the comment for `field` of the struct `struct_name`.
+    pub fn get(&self, struct_name: &str, field: &str) -> Option<&str>
+    {
+        self.fields
+            .get(struct_name)
+            .and_then(|fields| fields.get(field))
+            .map(String::as_str)
+    }
+}
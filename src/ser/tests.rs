@@ -102,4 +102,106 @@ fn test_char()
 fn test_escape()
 {
     assert_eq!(to_string(&r#""Quoted""#).unwrap(), r#""\"Quoted\"""#);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_comments()
+{
+    use self::pretty::{to_string_with_comments, Comments};
+
+    #[derive(Serialize)]
+    struct Config { volume: f32, mode: MyEnum }
+
+    let comments = Comments::new()
+        .field("Config", "volume", "Master volume\n\nBetween 0 and 1")
+        .field("D", "b", "Inside of a variant")
+        .field("Other", "mode", "Not used");
+
+    let config = Config { volume: 0.5, mode: MyEnum::D { a: 1, b: 2 } };
+
+    assert_eq!(to_string_with_comments(&config, &comments).unwrap(), "{
+    // Master volume
+    //
+    // Between 0 and 1
+    volume: 0.5,
+    mode: D{
+        a: 1,
+        // Inside of a variant
+        b: 2,
+    },
+}".replace("\n", NEWLINE));
+    assert_eq!(to_string(&config).unwrap(), "{volume:0.5,mode:D{a:1,b:2,},}");
+}
+
+#[test]
+fn test_ron()
+{
+    #[derive(Serialize)]
+    struct Sprite
+    {
+        name: String,
+        size: (u32, u32),
+        kind: MyEnum,
+    }
+
+    let sprite = Sprite {
+        name: "a\n\"b\"\u{1}".to_owned(),
+        size: (2, 3),
+        kind: MyEnum::D { a: 1, b: 2 },
+    };
+
+    assert_eq!(
+        to_string_with_options(&sprite, Options::ron()).unwrap(),
+        "(name:\"a\\n\\\"b\\\"\\u0001\",size:(2,3,),kind:D(a:1,b:2,),)",
+    );
+    assert_eq!(
+        to_string_with_options(&sprite, Options { struct_names: true, ..Options::default() }).unwrap(),
+        "Sprite{name:\"a\n\\\"b\\\"\u{1}\",size:(2,3,),kind:D{a:1,b:2,},}",
+    );
+    assert_eq!(
+        pretty::to_string_with_options(&MyStruct { x: 1.0, y: 2.0 }, Options::ron()).unwrap(),
+        "(\n    x: 1,\n    y: 2,\n)",
+    );
+}
+
+#[test]
+fn test_implicit_some()
+{
+    #[derive(Serialize)]
+    enum Inner
+    {
+        Some(u8),
+    }
+
+    let options = Options { implicit_some: true, ..Options::default() };
+
+    assert_eq!(to_string_with_options(&Some(1), options).unwrap(), "1");
+    assert_eq!(to_string_with_options(&Some(Some(1)), options).unwrap(), "1");
+    assert_eq!(to_string_with_options(&Some(None::<u8>), options).unwrap(), "Some(None)");
+    assert_eq!(to_string_with_options(&None::<u8>, options).unwrap(), "None");
+    assert_eq!(to_string_with_options(&Some(Inner::Some(1)), options).unwrap(), "Some(Some(1))");
+}
+
+#[test]
+fn test_unwrap_newtypes()
+{
+    #[derive(Serialize)]
+    struct Meters(f32);
+
+    #[derive(Serialize)]
+    enum Shape
+    {
+        Line(Meters),
+        Rect(MyStruct),
+        Pair((u8, u8)),
+    }
+
+    let options = Options { unwrap_newtypes: true, ..Options::default() };
+    let shapes = vec![Shape::Line(Meters(3.0)), Shape::Rect(MyStruct { x: 1.0, y: 2.0 }), Shape::Pair((1, 2))];
+
+    assert_eq!(to_string_with_options(&shapes, options).unwrap(), "[Line(3),Rect{x:1,y:2,},Pair((1,2,)),]");
+    assert_eq!(
+        to_string_with_options(&shapes, Options { dialect: Dialect::Ron, ..options }).unwrap(),
+        "[Line(3),Rect(x:1,y:2,),Pair((1,2,)),]",
+    );
+}
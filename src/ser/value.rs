@@ -1,6 +1,18 @@
+use std::fmt;
+
 use serde::ser::{Serialize, Serializer};
 
-use value::Value;
+use ser::pretty;
+use value::{Map, Value};
+
+impl fmt::Display for Value
+{
+    /// Writes the value as pretty RSON.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.write_str(&pretty::to_string(self).map_err(|_| fmt::Error)?)
+    }
+}
 
 impl Serialize for Value
 {
@@ -21,3 +33,13 @@ impl Serialize for Value
         }
     }
 }
+
+impl Serialize for Map
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer
+    {
+        serializer.collect_map(self)
+    }
+}
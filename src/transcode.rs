@@ -0,0 +1,255 @@
+//! Streaming RSON into any serde serializer.
+
+use std::cell::RefCell;
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use de::Deserializer;
+
+/// Reads one value from `deserializer` and writes it to `serializer`,
+/// without building an intermediate `Value`.
+///
+/// Options stay options, structs become maps and enum variants are
+/// written the way serde writes them for self-describing formats:
+/// a unit variant as its name and other variants as a map from the
+/// name to their content. Call `Deserializer::end` afterwards to make
+/// sure there's no trailing input.
+///
+/// ```
+/// extern crate rson_rs;
+/// extern crate serde_json;
+///
+/// use rson_rs::de::Deserializer;
+///
+/// # fn main() {
+/// let mut de = Deserializer::from_str("Sprite { color: Rgb(1, 0.5, 0), parent: None }");
+/// let mut json = Vec::new();
+///
+/// rson_rs::transcode(&mut de, &mut serde_json::Serializer::new(&mut json)).unwrap();
+/// de.end().unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(json).unwrap(),
+///     r#"{"color":{"Rgb":[1,0.5,0]},"parent":null}"#,
+/// );
+/// # }
+/// ```
+pub fn transcode<'de, S>(deserializer: &mut Deserializer<'de>, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer
+{
+    Transcoder::new(deserializer).serialize(serializer)
+}
+
+/// Serializes whatever the wrapped deserializer produces.
+struct Transcoder<D>(RefCell<Option<D>>);
+
+impl<D> Transcoder<D>
+{
+    fn new(deserializer: D) -> Self
+    {
+        Transcoder(RefCell::new(Some(deserializer)))
+    }
+}
+
+impl<'de, D> Serialize for Transcoder<D>
+    where D: de::Deserializer<'de>
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer
+    {
+        let deserializer = self.0.borrow_mut().take().expect("Bug: transcoder used twice");
+
+        deserializer.deserialize_any(Forward(serializer)).map_err(ser::Error::custom)
+    }
+}
+
+/// Forwards everything it visits to the serializer.
+struct Forward<S>(S);
+
+macro_rules! forward {
+    ($($visit:ident($ty:ty) => $serialize:ident,)*) => {
+        $(
+            fn $visit<E>(self, v: $ty) -> Result<Self::Value, E>
+                where E: de::Error
+            {
+                self.0.$serialize(v).map_err(E::custom)
+            }
+        )*
+    }
+}
+
+impl<'de, S> Visitor<'de> for Forward<S>
+    where S: Serializer
+{
+    type Value = S::Ok;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.write_str("any value")
+    }
+
+    forward! {
+        visit_bool(bool) => serialize_bool,
+        visit_i64(i64) => serialize_i64,
+        visit_u64(u64) => serialize_u64,
+        visit_f64(f64) => serialize_f64,
+        visit_char(char) => serialize_char,
+        visit_str(&str) => serialize_str,
+        visit_bytes(&[u8]) => serialize_bytes,
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        self.0.serialize_unit().map_err(E::custom)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+        where E: de::Error
+    {
+        self.0.serialize_none().map_err(E::custom)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        self.0.serialize_some(&Transcoder::new(deserializer)).map_err(de::Error::custom)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        Transcoder::new(deserializer).serialize(self.0).map_err(de::Error::custom)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where A: SeqAccess<'de>
+    {
+        let mut s = self.0.serialize_seq(seq.size_hint()).map_err(de::Error::custom)?;
+
+        while let Some(()) = seq.next_element_seed(Element(&mut s))? {}
+
+        s.end().map_err(de::Error::custom)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where A: MapAccess<'de>
+    {
+        let mut m = self.0.serialize_map(map.size_hint()).map_err(de::Error::custom)?;
+
+        while let Some(()) = map.next_key_seed(Key(&mut m))? {
+            map.next_value_seed(Value(&mut m))?;
+        }
+
+        m.end().map_err(de::Error::custom)
+    }
+}
+
+struct Element<'a, S: 'a>(&'a mut S);
+
+impl<'de, 'a, S> DeserializeSeed<'de> for Element<'a, S>
+    where S: SerializeSeq
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+        where D: de::Deserializer<'de>
+    {
+        self.0.serialize_element(&Transcoder::new(deserializer)).map_err(de::Error::custom)
+    }
+}
+
+struct Key<'a, S: 'a>(&'a mut S);
+
+impl<'de, 'a, S> DeserializeSeed<'de> for Key<'a, S>
+    where S: SerializeMap
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+        where D: de::Deserializer<'de>
+    {
+        self.0.serialize_key(&Transcoder::new(deserializer)).map_err(de::Error::custom)
+    }
+}
+
+struct Value<'a, S: 'a>(&'a mut S);
+
+impl<'de, 'a, S> DeserializeSeed<'de> for Value<'a, S>
+    where S: SerializeMap
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+        where D: de::Deserializer<'de>
+    {
+        self.0.serialize_value(&Transcoder::new(deserializer)).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use serde_json;
+
+    use super::*;
+
+    fn to_json(input: &str) -> String
+    {
+        let mut de = Deserializer::from_str(input);
+        let mut out = Vec::new();
+
+        transcode(&mut de, &mut serde_json::Serializer::new(&mut out)).expect("Failed to transcode");
+        de.end().unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_transcode()
+    {
+        assert_eq!(
+            to_json("Scene { name: \"a\", size: (1, 2), tags: [], owner: Some(None), unit: () }"),
+            r#"{"name":"a","size":[1,2],"tags":[],"owner":null,"unit":null}"#,
+        );
+        assert_eq!(to_json("{ \"x\": true }"), r#"{"x":true}"#);
+    }
+
+    #[test]
+    fn test_enums()
+    {
+        assert_eq!(
+            to_json("[Yellow, Wrap(\"x\"), Pair(1, 2), Empty(), Nested(Some(Inner(Yellow)))]"),
+            r#"["Yellow",{"Wrap":"x"},{"Pair":[1,2]},{"Empty":[]},{"Nested":{"Inner":"Yellow"}}]"#,
+        );
+        assert_eq!(
+            to_json("[One(\"a, b\"), One([1, 2]), One(\")\", ), One(/* , */ ','), Two({ a: 1 }, ')')]"),
+            r#"[{"One":"a, b"},{"One":[1,2]},{"One":")"},{"One":","},{"Two":[{"a":1},")"]}]"#,
+        );
+        assert_eq!(
+            to_json("Two(One([B(C(1), D(2, 3,), E(), F(4,))]), 5)"),
+            r#"{"Two":[{"One":[{"B":[{"C":1},{"D":[2,3]},{"E":[]},{"F":4}]}]},5]}"#,
+        );
+    }
+
+    #[test]
+    fn test_deep_nesting()
+    {
+        let depth = 200;
+        let input = format!("{}1, 2{}", "A(".repeat(depth), ")".repeat(depth));
+        let output = format!("{}[1,2]{}", "{\"A\":".repeat(depth), "}".repeat(depth));
+
+        assert_eq!(to_json(&input), output);
+    }
+
+    #[test]
+    fn test_error()
+    {
+        let mut de = Deserializer::from_str("[1, Pair(1, 2]");
+        let res = transcode(&mut de, &mut serde_json::Serializer::new(Vec::new()));
+
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use std::borrow::Cow;
+use std::iter::FromIterator;
+
+use value::{Map, Number, Value};
+
+macro_rules! from_number {
+    ($($ty:ty)*) => {
+        $(
+            impl From<$ty> for Value
+            {
+                fn from(n: $ty) -> Self
+                {
+                    Value::Number(Number::new(n as f64))
+                }
+            }
+        )*
+    };
+}
+
+from_number!(i8 i16 i32 i64 isize u8 u16 u32 u64 usize);
+
+impl From<f32> for Value
+{
+    /// Panics if `n` is not finite, like `Number::new`.
+    fn from(n: f32) -> Self
+    {
+        Value::Number(Number::new(f64::from(n)))
+    }
+}
+
+impl From<f64> for Value
+{
+    /// Panics if `n` is not finite, like `Number::new`.
+    fn from(n: f64) -> Self
+    {
+        Value::Number(Number::new(n))
+    }
+}
+
+impl From<Number> for Value
+{
+    fn from(n: Number) -> Self
+    {
+        Value::Number(n)
+    }
+}
+
+impl From<bool> for Value
+{
+    fn from(b: bool) -> Self
+    {
+        Value::Bool(b)
+    }
+}
+
+impl From<char> for Value
+{
+    fn from(c: char) -> Self
+    {
+        Value::Char(c)
+    }
+}
+
+impl<'a> From<&'a str> for Value
+{
+    fn from(s: &'a str) -> Self
+    {
+        Value::String(s.to_owned())
+    }
+}
+
+impl From<String> for Value
+{
+    fn from(s: String) -> Self
+    {
+        Value::String(s)
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for Value
+{
+    fn from(s: Cow<'a, str>) -> Self
+    {
+        Value::String(s.into_owned())
+    }
+}
+
+impl From<()> for Value
+{
+    fn from(_: ()) -> Self
+    {
+        Value::Unit
+    }
+}
+
+impl From<Map> for Value
+{
+    fn from(map: Map) -> Self
+    {
+        Value::Map(map)
+    }
+}
+
+impl<T> From<Option<T>> for Value
+    where T: Into<Value>
+{
+    fn from(o: Option<T>) -> Self
+    {
+        Value::Option(o.map(|v| Box::new(v.into())))
+    }
+}
+
+impl<T> From<Vec<T>> for Value
+    where T: Into<Value>
+{
+    fn from(items: Vec<T>) -> Self
+    {
+        items.into_iter().collect()
+    }
+}
+
+impl<'a, T> From<&'a [T]> for Value
+    where T: Clone + Into<Value>
+{
+    fn from(items: &'a [T]) -> Self
+    {
+        items.iter().cloned().collect()
+    }
+}
+
+impl<T> FromIterator<T> for Value
+    where T: Into<Value>
+{
+    fn from_iter<I>(iter: I) -> Self
+        where I: IntoIterator<Item = T>
+    {
+        Value::Seq(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for Value
+    where K: Into<Value>, V: Into<Value>
+{
+    fn from_iter<I>(iter: I) -> Self
+        where I: IntoIterator<Item = (K, V)>
+    {
+        Value::Map(iter.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
@@ -0,0 +1,168 @@
+use std::ops;
+
+use path::Segment;
+use value::{segment_key, Map, Value};
+
+/// Types which can index into a `Value`: `usize` for elements of
+/// sequences and `str` or `String` for entries with string keys.
+///
+/// Indexing with `[]` returns `Value::Unit` for missing entries. Assigning
+/// through `[]` inserts missing keys, turning `Value::Unit` into a map,
+/// and panics for indices out of bounds.
+///
+/// ```
+/// use rson_rs::value::Value;
+///
+/// let mut value = Value::Unit;
+/// value["sizes"] = vec![1, 2].into();
+/// value["sizes"][1] = 3.into();
+///
+/// assert_eq!(value.to_string(), "{\n    \"sizes\": [\n        1,\n        3,\n    ],\n}");
+/// ```
+pub trait Index: private::Sealed
+{
+    #[doc(hidden)]
+    fn segment(&self) -> Segment;
+
+    #[doc(hidden)]
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value>
+    {
+        value.child(&self.segment())
+    }
+
+    #[doc(hidden)]
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value>
+    {
+        value.child_mut(&self.segment())
+    }
+}
+
+impl Index for usize
+{
+    fn segment(&self) -> Segment
+    {
+        Segment::Index(*self)
+    }
+}
+
+/// Looks the key up as is, so reading doesn't allocate a segment.
+impl Index for str
+{
+    fn segment(&self) -> Segment
+    {
+        Segment::Field(self.to_owned())
+    }
+
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value>
+    {
+        match *value {
+            Value::Option(Some(ref inner)) => self.index_into(inner),
+            Value::Map(ref map) => map.get_str(self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value>
+    {
+        match *value {
+            Value::Option(Some(ref mut inner)) => self.index_into_mut(inner),
+            Value::Map(ref mut map) => map.get_str_mut(self),
+            _ => None,
+        }
+    }
+}
+
+impl Index for String
+{
+    fn segment(&self) -> Segment
+    {
+        Segment::Field(self.clone())
+    }
+
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value>
+    {
+        self.as_str().index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value>
+    {
+        self.as_str().index_into_mut(value)
+    }
+}
+
+impl<T> Index for &T
+    where T: Index + ?Sized
+{
+    fn segment(&self) -> Segment
+    {
+        (**self).segment()
+    }
+
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value>
+    {
+        (**self).index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value>
+    {
+        (**self).index_into_mut(value)
+    }
+}
+
+mod private
+{
+    pub trait Sealed {}
+
+    impl Sealed for usize {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl<T> Sealed for &T where T: Sealed + ?Sized {}
+}
+
+static UNIT: Value = Value::Unit;
+
+impl<I> ops::Index<I> for Value
+    where I: Index
+{
+    type Output = Value;
+
+    fn index(&self, index: I) -> &Value
+    {
+        index.index_into(self).unwrap_or(&UNIT)
+    }
+}
+
+impl<I> ops::IndexMut<I> for Value
+    where I: Index
+{
+    fn index_mut(&mut self, index: I) -> &mut Value
+    {
+        index_or_insert(self, &index.segment())
+    }
+}
+
+fn index_or_insert<'v>(value: &'v mut Value, segment: &Segment) -> &'v mut Value
+{
+    if value.is_unit() {
+        *value = Value::Map(Map::new());
+    }
+
+    match (value, segment) {
+        (Value::Option(Some(inner)), _) => index_or_insert(inner, segment),
+        (Value::Map(map), _) => {
+            let key = segment_key(segment);
+
+            if !map.contains_key(&key) {
+                map.insert(key.clone(), Value::Unit);
+            }
+
+            map.get_mut(&key).expect("Bug: key was just inserted")
+        }
+        (Value::Seq(items), &Segment::Index(i)) => {
+            let len = items.len();
+
+            items.get_mut(i).unwrap_or_else(|| panic!("Index {} out of bounds for a sequence of length {}", i, len))
+        }
+        _ => panic!("Cannot index with `{}` into a value which isn't a map or a sequence", segment),
+    }
+}
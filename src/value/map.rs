@@ -0,0 +1,476 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+
+#[cfg(not(feature = "preserve_order"))]
+use std::borrow::Borrow;
+#[cfg(not(feature = "preserve_order"))]
+use std::collections::{btree_map, BTreeMap};
+
+#[cfg(feature = "preserve_order")]
+use indexmap::{map as index_map, Equivalent, IndexMap};
+#[cfg(feature = "preserve_order")]
+use std::mem;
+
+use value::Value;
+
+#[cfg(not(feature = "preserve_order"))]
+type Inner = BTreeMap<Value, Value>;
+#[cfg(not(feature = "preserve_order"))]
+type IntoIterInner = btree_map::IntoIter<Value, Value>;
+#[cfg(not(feature = "preserve_order"))]
+type IterInner<'a> = btree_map::Iter<'a, Value, Value>;
+#[cfg(not(feature = "preserve_order"))]
+type IterMutInner<'a> = btree_map::IterMut<'a, Value, Value>;
+
+#[cfg(feature = "preserve_order")]
+type Inner = IndexMap<Value, Value>;
+#[cfg(feature = "preserve_order")]
+type IntoIterInner = index_map::IntoIter<Value, Value>;
+#[cfg(feature = "preserve_order")]
+type IterInner<'a> = index_map::Iter<'a, Value, Value>;
+#[cfg(feature = "preserve_order")]
+type IterMutInner<'a> = index_map::IterMut<'a, Value, Value>;
+
+/// The map of a `Value::Map`.
+///
+/// Entries are sorted by key, unless the `preserve_order` feature is
+/// enabled, which keeps them in insertion order so documents can be
+/// loaded and written back without reordering them. Comparing and
+/// hashing maps doesn't depend on the order either way.
+#[derive(Clone, Default)]
+pub struct Map(Inner);
+
+impl Map
+{
+    pub fn new() -> Self
+    {
+        Map(Inner::new())
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &Value) -> bool
+    {
+        self.0.contains_key(key)
+    }
+
+    pub fn get(&self, key: &Value) -> Option<&Value>
+    {
+        self.0.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &Value) -> Option<&mut Value>
+    {
+        self.0.get_mut(key)
+    }
+
+    /// Looks up a string key without building a `Value` for it.
+    pub(crate) fn get_str(&self, key: &str) -> Option<&Value>
+    {
+        #[cfg(not(feature = "preserve_order"))]
+        return self.0.get(&StrKey(key) as &dyn Key);
+        #[cfg(feature = "preserve_order")]
+        return self.0.get(&StrKey(key));
+    }
+
+    pub(crate) fn get_str_mut(&mut self, key: &str) -> Option<&mut Value>
+    {
+        #[cfg(not(feature = "preserve_order"))]
+        return self.0.get_mut(&StrKey(key) as &dyn Key);
+        #[cfg(feature = "preserve_order")]
+        return self.0.get_mut(&StrKey(key));
+    }
+
+    /// Inserts a value, returning the old value of `key`. A key
+    /// which is already present keeps its place.
+    pub fn insert(&mut self, key: Value, value: Value) -> Option<Value>
+    {
+        self.0.insert(key, value)
+    }
+
+    /// Removes a key, keeping the order of the other entries.
+    pub fn remove(&mut self, key: &Value) -> Option<Value>
+    {
+        #[cfg(not(feature = "preserve_order"))]
+        return self.0.remove(key);
+        #[cfg(feature = "preserve_order")]
+        return self.0.shift_remove(key);
+    }
+
+    pub fn iter(&self) -> Iter<'_>
+    {
+        Iter(self.0.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_>
+    {
+        IterMut(self.0.iter_mut())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Value>
+    {
+        self.0.keys()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value>
+    {
+        self.0.values()
+    }
+
+    /// The entries sorted by key, for order independent comparisons.
+    #[cfg(feature = "preserve_order")]
+    fn sorted(&self) -> Vec<(&Value, &Value)>
+    {
+        let mut entries: Vec<_> = self.0.iter().collect();
+        entries.sort();
+
+        entries
+    }
+}
+
+impl fmt::Debug for Map
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.debug_map().entries(self.0.iter()).finish()
+    }
+}
+
+impl PartialEq for Map
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Map {}
+
+impl PartialOrd for Map
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering>
+    {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Map
+{
+    fn cmp(&self, other: &Self) -> Ordering
+    {
+        #[cfg(not(feature = "preserve_order"))]
+        return self.0.cmp(&other.0);
+        #[cfg(feature = "preserve_order")]
+        return self.sorted().cmp(&other.sorted());
+    }
+}
+
+impl Hash for Map
+{
+    fn hash<H: Hasher>(&self, state: &mut H)
+    {
+        #[cfg(not(feature = "preserve_order"))]
+        self.0.hash(state);
+        #[cfg(feature = "preserve_order")]
+        self.sorted().hash(state);
+    }
+}
+
+impl FromIterator<(Value, Value)> for Map
+{
+    fn from_iter<I>(iter: I) -> Self
+        where I: IntoIterator<Item = (Value, Value)>
+    {
+        Map(iter.into_iter().collect())
+    }
+}
+
+impl Extend<(Value, Value)> for Map
+{
+    fn extend<I>(&mut self, iter: I)
+        where I: IntoIterator<Item = (Value, Value)>
+    {
+        self.0.extend(iter);
+    }
+}
+
+impl IntoIterator for Map
+{
+    type Item = (Value, Value);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter
+    {
+        IntoIter(self.0.into_iter())
+    }
+}
+
+impl<'a> IntoIterator for &'a Map
+{
+    type Item = (&'a Value, &'a Value);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a>
+    {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Map
+{
+    type Item = (&'a Value, &'a mut Value);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> IterMut<'a>
+    {
+        self.iter_mut()
+    }
+}
+
+/// A string key, which compares and hashes like `Value::String`.
+struct StrKey<'a>(&'a str);
+
+#[cfg(feature = "preserve_order")]
+impl<'a> Hash for StrKey<'a>
+{
+    fn hash<H: Hasher>(&self, state: &mut H)
+    {
+        mem::discriminant(&Value::String(String::new())).hash(state);
+        self.0.hash(state);
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl<'a> Equivalent<Value> for StrKey<'a>
+{
+    fn equivalent(&self, key: &Value) -> bool
+    {
+        key.as_str() == Some(self.0)
+    }
+}
+
+/// The keys a `BTreeMap` can be searched with, since it needs a type
+/// which `Value` borrows as.
+#[cfg(not(feature = "preserve_order"))]
+trait Key
+{
+    fn cmp_key(&self, other: &dyn Key) -> Ordering;
+
+    fn value(&self) -> Option<&Value>;
+}
+
+#[cfg(not(feature = "preserve_order"))]
+impl Key for Value
+{
+    fn cmp_key(&self, other: &dyn Key) -> Ordering
+    {
+        match other.value() {
+            Some(value) => self.cmp(value),
+            None => other.cmp_key(self).reverse(),
+        }
+    }
+
+    fn value(&self) -> Option<&Value>
+    {
+        Some(self)
+    }
+}
+
+#[cfg(not(feature = "preserve_order"))]
+impl<'a> Key for StrKey<'a>
+{
+    fn cmp_key(&self, other: &dyn Key) -> Ordering
+    {
+        match other.value() {
+            Some(Value::String(s)) => self.0.cmp(s),
+            // Only the variants are compared, and an empty `String` doesn't allocate.
+            Some(value) => Value::String(String::new()).cmp(value),
+            None => panic!("Bug: compared two string keys"),
+        }
+    }
+
+    fn value(&self) -> Option<&Value>
+    {
+        None
+    }
+}
+
+#[cfg(not(feature = "preserve_order"))]
+impl<'a> Borrow<dyn Key + 'a> for Value
+{
+    fn borrow(&self) -> &(dyn Key + 'a)
+    {
+        self
+    }
+}
+
+#[cfg(not(feature = "preserve_order"))]
+impl<'a> PartialEq for dyn Key + 'a
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.cmp_key(other) == Ordering::Equal
+    }
+}
+
+#[cfg(not(feature = "preserve_order"))]
+impl<'a> Eq for dyn Key + 'a {}
+
+#[cfg(not(feature = "preserve_order"))]
+impl<'a> PartialOrd for dyn Key + 'a
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering>
+    {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(not(feature = "preserve_order"))]
+impl<'a> Ord for dyn Key + 'a
+{
+    fn cmp(&self, other: &Self) -> Ordering
+    {
+        self.cmp_key(other)
+    }
+}
+
+/// An owning iterator over the entries of a `Map`.
+pub struct IntoIter(IntoIterInner);
+
+impl Iterator for IntoIter
+{
+    type Item = (Value, Value);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>)
+    {
+        self.0.size_hint()
+    }
+}
+
+/// An iterator over the entries of a `Map`.
+pub struct Iter<'a>(IterInner<'a>);
+
+impl<'a> Iterator for Iter<'a>
+{
+    type Item = (&'a Value, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>)
+    {
+        self.0.size_hint()
+    }
+}
+
+/// A mutable iterator over the entries of a `Map`.
+pub struct IterMut<'a>(IterMutInner<'a>);
+
+impl<'a> Iterator for IterMut<'a>
+{
+    type Item = (&'a Value, &'a mut Value);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>)
+    {
+        self.0.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::collections::hash_map::DefaultHasher;
+
+    use super::*;
+    use value::Number;
+
+    fn num(n: f64) -> Value
+    {
+        Value::Number(Number::new(n))
+    }
+
+    #[test]
+    fn test_order()
+    {
+        let mut map: Map = vec![(num(3.0), num(0.0)), (num(1.0), num(0.0)), (num(2.0), num(0.0))].into_iter().collect();
+        map.remove(&num(1.0));
+        map.insert(num(0.0), num(0.0));
+
+        let keys: Vec<_> = map.keys().cloned().collect();
+
+        if cfg!(feature = "preserve_order") {
+            assert_eq!(keys, [num(3.0), num(2.0), num(0.0)]);
+        } else {
+            assert_eq!(keys, [num(0.0), num(2.0), num(3.0)]);
+        }
+
+        let mut entries: Vec<_> = map.clone().into_iter().collect();
+        entries.reverse();
+
+        let reversed: Map = entries.into_iter().collect();
+        let hash = |m: &Map| {
+            let mut hasher = DefaultHasher::new();
+            m.hash(&mut hasher);
+
+            hasher.finish()
+        };
+
+        assert_eq!(map, reversed);
+        assert_eq!(map.cmp(&reversed), Ordering::Equal);
+        assert_eq!(hash(&map), hash(&reversed));
+    }
+
+    #[test]
+    fn test_get_str()
+    {
+        let mut map = Value::from_str("{ \"b\": 1, 'a': 2, \"\": 3, [\"a\"]: 4, \"a\": 5, (): 6, true: 7, 8: 8 }")
+            .unwrap()
+            .as_map()
+            .cloned()
+            .unwrap();
+
+        assert_eq!(map.get_str("a"), Some(&num(5.0)));
+        assert_eq!(map.get_str("b"), Some(&num(1.0)));
+        assert_eq!(map.get_str(""), Some(&num(3.0)));
+        assert_eq!(map.get_str("c"), None);
+
+        *map.get_str_mut("b").unwrap() = num(9.0);
+
+        assert_eq!(map.get(&Value::String("b".to_owned())), Some(&num(9.0)));
+    }
+
+    #[test]
+    fn test_roundtrip()
+    {
+        let value = Value::from_str("{ \"b\": 1, \"a\": 2 }").unwrap();
+        let expected = if cfg!(feature = "preserve_order") {
+            "{\"b\":1,\"a\":2,}"
+        } else {
+            "{\"a\":2,\"b\":1,}"
+        };
+
+        assert_eq!(::ser::to_string(&value).unwrap(), expected);
+    }
+}
@@ -0,0 +1,401 @@
+//! Value module.
+
+use std::cmp::{Eq, Ordering};
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+use serde::Serialize;
+
+use ser;
+
+pub use self::index::Index;
+pub use self::map::Map;
+pub use self::spanned::{SpannedKind, SpannedValue};
+
+mod from;
+mod index;
+pub mod map;
+mod query;
+mod serializer;
+mod spanned;
+
+pub(crate) use self::query::{key_segment, segment_key};
+
+/// Converts a serializable value into a `Value`.
+///
+/// The result is the same as parsing the RSON text of `value`, so struct
+/// names are dropped. Non-finite floats, which have no `Value`, are errors.
+pub fn to_value<T>(value: &T) -> ser::Result<Value>
+    where T: Serialize
+{
+    value.serialize(self::serializer::Serializer)
+}
+
+/// A wrapper for `f64` which guarantees that the inner value
+/// is finite and thus implements `Eq`, `Hash` and `Ord`.
+#[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
+pub struct Number(f64);
+
+impl Number
+{
+    /// Panics if `v` is not a real number
+    /// (infinity, NaN, ..).
+    pub fn new(v: f64) -> Self
+    {
+        if !v.is_finite() {
+            panic!("Tried to create Number with a NaN / infinity");
+        }
+
+        Number(v)
+    }
+
+    /// Returns the wrapped float.
+    pub fn get(&self) -> f64
+    {
+        self.0
+    }
+}
+
+impl Eq for Number
+{
+}
+
+impl Hash for Number
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.0 as u64);
+    }
+}
+
+impl Ord for Number
+{
+    fn cmp(&self, other: &Self) -> Ordering
+    {
+        self.partial_cmp(other).expect("Bug: Contract violation")
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Value
+{
+    Bool(bool),
+    Char(char),
+    Map(Map),
+    Number(Number),
+    Option(Option<Box<Value>>),
+    String(String),
+    Seq(Vec<Value>),
+    #[default]
+    Unit,
+}
+
+/// Written out so string keys can be hashed the same way without a `Value`.
+impl Hash for Value
+{
+    fn hash<H: Hasher>(&self, state: &mut H)
+    {
+        mem::discriminant(self).hash(state);
+
+        match *self {
+            Value::Bool(b) => b.hash(state),
+            Value::Char(c) => c.hash(state),
+            Value::Map(ref map) => map.hash(state),
+            Value::Number(n) => n.hash(state),
+            Value::Option(ref o) => o.hash(state),
+            Value::String(ref s) => s.hash(state),
+            Value::Seq(ref items) => items.hash(state),
+            Value::Unit => {}
+        }
+    }
+}
+
+/// Accessors, which return `None` if the value is of another type.
+///
+/// ```
+/// use rson_rs::value::Value;
+///
+/// let value: Value = "{ name: \"guy\", items: [1, 2.5], pet: Some(Dog) }".parse().unwrap();
+///
+/// assert_eq!(value["name"].as_str(), Some("guy"));
+/// assert_eq!(value["items"][0].as_u64(), Some(1));
+/// assert_eq!(value["items"][1].as_i64(), None);
+/// assert_eq!(value["pet"].as_str(), None);
+/// assert_eq!(value["pet"].as_option().and_then(|pet| pet?.as_str()), Some("Dog"));
+/// assert!(value["missing"].is_unit());
+/// ```
+impl Value
+{
+    pub fn is_bool(&self) -> bool
+    {
+        self.as_bool().is_some()
+    }
+
+    pub fn as_bool(&self) -> Option<bool>
+    {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn is_char(&self) -> bool
+    {
+        self.as_char().is_some()
+    }
+
+    pub fn as_char(&self) -> Option<char>
+    {
+        match *self {
+            Value::Char(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn is_map(&self) -> bool
+    {
+        self.as_map().is_some()
+    }
+
+    pub fn as_map(&self) -> Option<&Map>
+    {
+        match *self {
+            Value::Map(ref map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_map_mut(&mut self) -> Option<&mut Map>
+    {
+        match *self {
+            Value::Map(ref mut map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn is_number(&self) -> bool
+    {
+        self.as_f64().is_some()
+    }
+
+    pub fn as_f64(&self) -> Option<f64>
+    {
+        match *self {
+            Value::Number(n) => Some(n.get()),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if it is an integer which fits into an `i64`.
+    pub fn as_i64(&self) -> Option<i64>
+    {
+        match self.as_f64() {
+            Some(n) if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 => Some(n as i64),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if it is an integer which fits into a `u64`.
+    pub fn as_u64(&self) -> Option<u64>
+    {
+        match self.as_f64() {
+            Some(n) if n.fract() == 0.0 && n >= 0.0 && n < u64::MAX as f64 => Some(n as u64),
+            _ => None,
+        }
+    }
+
+    pub fn is_option(&self) -> bool
+    {
+        self.as_option().is_some()
+    }
+
+    /// Returns `Some(None)` for `None` and `Some(Some(value))` for `Some(value)`.
+    pub fn as_option(&self) -> Option<Option<&Value>>
+    {
+        match *self {
+            Value::Option(ref o) => Some(o.as_ref().map(|v| &**v)),
+            _ => None,
+        }
+    }
+
+    pub fn is_string(&self) -> bool
+    {
+        self.as_str().is_some()
+    }
+
+    pub fn as_str(&self) -> Option<&str>
+    {
+        match *self {
+            Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn is_seq(&self) -> bool
+    {
+        self.as_seq().is_some()
+    }
+
+    pub fn as_seq(&self) -> Option<&Vec<Value>>
+    {
+        match *self {
+            Value::Seq(ref items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_seq_mut(&mut self) -> Option<&mut Vec<Value>>
+    {
+        match *self {
+            Value::Seq(ref mut items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn is_unit(&self) -> bool
+    {
+        *self == Value::Unit
+    }
+
+    /// Returns an element of a sequence or an entry of a map, by
+    /// index or key. Like paths, this looks through `Some`.
+    pub fn get<I>(&self, index: I) -> Option<&Value>
+        where I: Index
+    {
+        index.index_into(self)
+    }
+
+    pub fn get_mut<I>(&mut self, index: I) -> Option<&mut Value>
+        where I: Index
+    {
+        index.index_into_mut(self)
+    }
+
+    /// Takes the value out, leaving `Value::Unit` in its place.
+    pub fn take(&mut self) -> Value
+    {
+        mem::take(self)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn test_accessors()
+    {
+        let mut value: Value = "{ \"a\": [1, -2, 0.5, 'c'], \"b\": Some(true), 3: () }".parse().unwrap();
+
+        assert_eq!(value["a"][0].as_u64(), Some(1));
+        assert_eq!(value["a"][1].as_u64(), None);
+        assert_eq!(value["a"][1].as_i64(), Some(-2));
+        assert_eq!(value["a"][2].as_f64(), Some(0.5));
+        assert_eq!(value["a"][3].as_char(), Some('c'));
+        assert_eq!(value["b"].as_bool(), None);
+        assert_eq!(value["b"].as_option(), Some(Some(&Value::Bool(true))));
+        assert!(value[3].is_unit() && value.get(3).is_some());
+        assert_eq!(value.get("a").and_then(Value::as_seq).map(Vec::len), Some(4));
+        let key = &"c".to_owned();
+        assert_eq!(value.get(key), None);
+        assert!(value["c"][7].is_unit());
+
+        let a = value.get_mut("a").unwrap().take();
+
+        assert_eq!(a.as_seq().map(Vec::len), Some(4));
+        assert!(value["a"].is_unit());
+        assert!("1 2".parse::<Value>().is_err());
+    }
+
+    #[test]
+    fn test_from()
+    {
+        let value: Value = vec![("a", Value::from(1u8)), ("b", Value::from(vec![Some("x"), None]))]
+            .into_iter()
+            .collect();
+
+        assert_eq!(value, "{ \"a\": 1, \"b\": [Some(\"x\"), None] }".parse().unwrap());
+        assert_eq!(Value::from(&['a', 'b'][..]), Value::Seq(vec!['a'.into(), 'b'.into()]));
+        assert_eq!(Value::from(()), Value::Unit);
+        assert_eq!(Value::from(2.5f32).as_f64(), Some(2.5));
+    }
+
+    #[test]
+    fn test_index_mut()
+    {
+        let mut value = Value::from(Some(Value::Unit));
+
+        value["a"]["b"] = true.into();
+        value["a"]["c"] = Value::Seq(vec![0.into()]);
+        value["a"]["c"][0] = "x".into();
+        value["a"][5] = 'y'.into();
+
+        assert_eq!(value, "Some({ \"a\": { \"b\": true, \"c\": [\"x\"], 5: 'y' } })".parse().unwrap());
+        assert_eq!(value.to_string().parse::<Value>().unwrap(), value);
+    }
+
+    #[test]
+    fn test_to_value()
+    {
+        use std::collections::BTreeMap;
+        use std::f64;
+
+        use de;
+
+        #[derive(Serialize)]
+        struct Unit;
+
+        #[derive(Serialize)]
+        struct Newtype(f32);
+
+        #[derive(Serialize)]
+        struct Tuple(Unit, i8, ());
+
+        #[derive(Serialize)]
+        struct Empty();
+
+        #[derive(Serialize)]
+        enum Enum
+        {
+            A,
+            B(Option<Newtype>),
+            C(u8, bool),
+            D { x: i64 },
+            E(),
+        }
+
+        #[derive(Serialize)]
+        struct Struct
+        {
+            unit: Unit,
+            tuple: Tuple,
+            empty: Empty,
+            enums: Vec<Enum>,
+            map: BTreeMap<(u8, char), Option<&'static str>>,
+        }
+
+        let value = Struct {
+            unit: Unit,
+            tuple: Tuple(Unit, -1, ()),
+            empty: Empty(),
+            enums: vec![Enum::A, Enum::B(Some(Newtype(1.5))), Enum::B(None), Enum::C(2, true), Enum::D { x: 3 }, Enum::E()],
+            map: vec![((1, 'a'), Some("x")), ((2, 'b'), None)].into_iter().collect(),
+        };
+        let text: Value = de::from_str(&ser::to_string(&value).unwrap()).unwrap();
+
+        assert_eq!(to_value(&value).unwrap(), text);
+        assert_eq!(to_value(&Newtype(1.5)).unwrap(), "(1.5)".parse().unwrap());
+        assert!(to_value(&Newtype(f32::NAN)).is_err());
+        assert!(to_value(&vec![f64::INFINITY]).is_err());
+        assert!(to_value(&Some(f64::NEG_INFINITY)).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Index 1 out of bounds for a sequence of length 1")]
+    fn test_index_out_of_bounds()
+    {
+        let mut value = Value::from(vec![1]);
+
+        value[1] = 2.into();
+    }
+}
@@ -0,0 +1,278 @@
+use path::{Comparison, ParsePathError, Path, Query, Segment, Step};
+use value::{Number, Value};
+
+/// Looking up values by path, see the `path` module for the syntax.
+///
+/// Struct fields are map entries with string keys, and `Some` is
+/// looked through, so `owner.name` also works if `owner` is optional.
+///
+/// ```
+/// use rson_rs::value::Value;
+///
+/// let mut value = Value::from_str("{
+///     buildings: [
+///         { size: (10, 20), owner: None },
+///         { size: (20, 25), owner: Some(\"guy\") },
+///     ],
+/// }").unwrap();
+///
+/// assert_eq!(value.pointer("buildings[1].size.0"), Value::from_str("20").ok().as_ref());
+/// assert_eq!(value.query("buildings[owner != None].size[*]").unwrap().len(), 2);
+///
+/// for owner in value.query_mut("buildings[*].owner").unwrap() {
+///     *owner = Value::Option(None);
+/// }
+/// ```
+impl Value
+{
+    /// Returns the value at `path`, or `None` if there is no such value
+    /// or the path is invalid.
+    pub fn pointer(&self, path: &str) -> Option<&Value>
+    {
+        self.get_path(&path.parse().ok()?)
+    }
+
+    /// Like `pointer`, but returns a mutable reference.
+    pub fn pointer_mut(&mut self, path: &str) -> Option<&mut Value>
+    {
+        self.get_path_mut(&path.parse().ok()?)
+    }
+
+    pub fn get_path(&self, path: &Path) -> Option<&Value>
+    {
+        path.segments().iter().try_fold(self, |value, segment| value.child(segment))
+    }
+
+    pub fn get_path_mut(&mut self, path: &Path) -> Option<&mut Value>
+    {
+        path.segments().iter().try_fold(self, |value, segment| value.child_mut(segment))
+    }
+
+    /// Returns all values matching `query`, in document order.
+    pub fn query(&self, query: &str) -> Result<Vec<&Value>, ParsePathError>
+    {
+        Ok(self.select(&query.parse()?))
+    }
+
+    /// Like `query`, but returns mutable references.
+    pub fn query_mut(&mut self, query: &str) -> Result<Vec<&mut Value>, ParsePathError>
+    {
+        Ok(self.select_mut(&query.parse()?))
+    }
+
+    /// Like `query`, for an already parsed query.
+    pub fn select(&self, query: &Query) -> Vec<&Value>
+    {
+        let mut found = Vec::new();
+        select(self, query.steps(), &mut found);
+
+        found
+    }
+
+    /// Like `query_mut`, for an already parsed query.
+    pub fn select_mut(&mut self, query: &Query) -> Vec<&mut Value>
+    {
+        let mut found = Vec::new();
+        select_mut(self, query.steps(), &mut found);
+
+        found
+    }
+
+    pub(super) fn child(&self, segment: &Segment) -> Option<&Value>
+    {
+        match (self, segment) {
+            (Value::Option(Some(value)), _) => value.child(segment),
+            (Value::Map(map), _) => map.get(&segment_key(segment)),
+            (Value::Seq(items), &Segment::Index(i)) => items.get(i),
+            _ => None,
+        }
+    }
+
+    pub(super) fn child_mut(&mut self, segment: &Segment) -> Option<&mut Value>
+    {
+        match (self, segment) {
+            (Value::Option(Some(value)), _) => value.child_mut(segment),
+            (Value::Map(map), _) => map.get_mut(&segment_key(segment)),
+            (Value::Seq(items), &Segment::Index(i)) => items.get_mut(i),
+            _ => None,
+        }
+    }
+
+    /// The elements of a sequence or the values of a map.
+    fn children(&self) -> Vec<&Value>
+    {
+        match *self {
+            Value::Option(Some(ref value)) => value.children(),
+            Value::Map(ref map) => map.values().collect(),
+            Value::Seq(ref items) => items.iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Value>
+    {
+        match *self {
+            Value::Option(Some(ref mut value)) => value.children_mut(),
+            Value::Map(ref mut map) => map.iter_mut().map(|(_, v)| v).collect(),
+            Value::Seq(ref mut items) => items.iter_mut().collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// The map key a segment stands for: a string for a field and
+/// a number for an index.
+pub(crate) fn segment_key(segment: &Segment) -> Value
+{
+    match *segment {
+        Segment::Field(ref name) => Value::String(name.clone()),
+        Segment::Index(i) => Value::Number(Number::new(i as f64)),
+    }
+}
+
+/// The segment for a map key, if it can be written in a path.
+pub(crate) fn key_segment(key: &Value) -> Option<Segment>
+{
+    match *key {
+        Value::String(ref s) => Some(Segment::Field(s.clone())),
+        Value::Number(_) => key.as_u64().map(|i| Segment::Index(i as usize)),
+        _ => None,
+    }
+}
+
+/// Checks a filter. Values without anything at `path` never match.
+fn matches(value: &Value, path: &Path, comparison: Comparison, expected: &Value) -> bool
+{
+    match (value.get_path(path), comparison) {
+        (Some(found), Comparison::Equal) => found == expected,
+        (Some(found), Comparison::NotEqual) => found != expected,
+        (None, _) => false,
+    }
+}
+
+fn select<'a>(value: &'a Value, steps: &[Step], found: &mut Vec<&'a Value>)
+{
+    let (step, rest) = match steps.split_first() {
+        Some(split) => split,
+        None => return found.push(value),
+    };
+
+    match *step {
+        Step::Segment(ref segment) => {
+            if let Some(child) = value.child(segment) {
+                select(child, rest, found);
+            }
+        }
+        Step::Wildcard => {
+            for child in value.children() {
+                select(child, rest, found);
+            }
+        }
+        Step::Filter(ref path, comparison, ref expected) => {
+            for child in value.children() {
+                if matches(child, path, comparison, expected) {
+                    select(child, rest, found);
+                }
+            }
+        }
+    }
+}
+
+fn select_mut<'a>(value: &'a mut Value, steps: &[Step], found: &mut Vec<&'a mut Value>)
+{
+    let (step, rest) = match steps.split_first() {
+        Some(split) => split,
+        None => return found.push(value),
+    };
+
+    match *step {
+        Step::Segment(ref segment) => {
+            if let Some(child) = value.child_mut(segment) {
+                select_mut(child, rest, found);
+            }
+        }
+        Step::Wildcard => {
+            for child in value.children_mut() {
+                select_mut(child, rest, found);
+            }
+        }
+        Step::Filter(ref path, comparison, ref expected) => {
+            for child in value.children_mut() {
+                if matches(child, path, comparison, expected) {
+                    select_mut(child, rest, found);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const GAME: &str = "Game {
+        title: \"Hello, RSON!\",
+        level: Level {
+            buildings: [
+                { size: (10, 20), color: Yellow, owner: None },
+                { size: (20, 25), color: Custom(0.1, 0.8, 1.0), owner: Some(\"guy\") },
+            ],
+            characters: { \"guy\": { friendly: true }, 3: { friendly: false } },
+        },
+    }";
+
+    fn value(s: &str) -> Value
+    {
+        Value::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_pointer()
+    {
+        let game = value(GAME);
+
+        assert_eq!(game.pointer(""), Some(&game));
+        assert_eq!(game.pointer("level.buildings[1].size.0"), Some(&value("20")));
+        assert_eq!(game.pointer("level.buildings[0].color"), Some(&value("Yellow")));
+        assert_eq!(game.pointer("level.buildings[1].color.Custom[2]"), Some(&value("1.0")));
+        assert_eq!(game.pointer("level.characters[\"guy\"].friendly"), Some(&value("true")));
+        assert_eq!(game.pointer("level.characters.3.friendly"), Some(&value("false")));
+        assert_eq!(game.pointer("level.buildings[2]"), None);
+        assert_eq!(game.pointer("title.0"), None);
+        assert_eq!(game.pointer("level..buildings"), None);
+    }
+
+    #[test]
+    fn test_query()
+    {
+        let game = value(GAME);
+        let query = |q: &str| game.query(q).unwrap();
+
+        assert_eq!(query("level.buildings[*].owner"), [&value("None"), &value("Some(\"guy\")")]);
+        assert_eq!(query("level.buildings[owner == Some(\"guy\")].size.0"), [&value("20")]);
+        assert_eq!(query("level.buildings[ size.1 != 20 ].color.*[*]"), [&value("0.1"), &value("0.8"), &value("1.0")]);
+        assert_eq!(query("level.characters[friendly == true]").len(), 1);
+        assert_eq!(query("level.buildings[missing != 1]").len(), 0);
+        assert_eq!(query("title[*]").len(), 0);
+        assert!(game.query("level.buildings[owner]").is_err());
+    }
+
+    #[test]
+    fn test_mut()
+    {
+        let mut game = value(GAME);
+
+        *game.pointer_mut("level.buildings[0].owner").unwrap() = value("Some(\"other\")");
+
+        for size in game.query_mut("level.buildings[*].size[*]").unwrap() {
+            *size = value("0");
+        }
+
+        assert_eq!(game.query("level.buildings[*].owner").unwrap(), [
+            &value("Some(\"other\")"),
+            &value("Some(\"guy\")"),
+        ]);
+        assert_eq!(game.pointer("level.buildings[1].size"), Some(&value("(0, 0)")));
+    }
+}
@@ -0,0 +1,392 @@
+use serde::ser::{self, Serialize};
+
+use ser::{Error, Result};
+use value::{Map, Number, Value};
+
+/// Builds a `Value` from serialized data, giving the same result as
+/// parsing the RSON text of the data.
+pub struct Serializer;
+
+impl ser::Serializer for Serializer
+{
+    type Ok = Value;
+    type Error = Error;
+
+    type SerializeSeq = SerializeSeq;
+    type SerializeTuple = SerializeSeq;
+    type SerializeTupleStruct = SerializeSeq;
+    type SerializeTupleVariant = SerializeSeq;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, v: bool) -> Result<Value>
+    {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value>
+    {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value>
+    {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value>
+    {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value>
+    {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value>
+    {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value>
+    {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value>
+    {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value>
+    {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value>
+    {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value>
+    {
+        if v.is_finite() {
+            Ok(Value::Number(Number::new(v)))
+        } else {
+            Err(Error::Message(format!("`{}` can't be stored in a `Value`", v)))
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value>
+    {
+        Ok(Value::Char(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value>
+    {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value>
+    {
+        v.serialize(self)
+    }
+
+    fn serialize_none(self) -> Result<Value>
+    {
+        Ok(Value::Option(None))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Value>
+        where T: ?Sized + Serialize
+    {
+        Ok(Value::Option(Some(Box::new(value.serialize(self)?))))
+    }
+
+    fn serialize_unit(self) -> Result<Value>
+    {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Value>
+    {
+        Ok(Value::Unit)
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str) -> Result<Value>
+    {
+        self.serialize_str(variant)
+    }
+
+    /// Written as `(value)`, which is a tuple.
+    fn serialize_newtype_struct<T>(self, _: &'static str, value: &T) -> Result<Value>
+        where T: ?Sized + Serialize
+    {
+        Ok(Value::Seq(vec![value.serialize(self)?]))
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value>
+        where T: ?Sized + Serialize
+    {
+        Ok(tagged(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeSeq>
+    {
+        Ok(SerializeSeq {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+            tuple: false,
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeSeq>
+    {
+        Ok(SerializeSeq {
+            items: Vec::with_capacity(len),
+            tuple: true,
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<SerializeSeq>
+    {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeSeq>
+    {
+        Ok(SerializeSeq {
+            items: Vec::with_capacity(len),
+            tuple: false,
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<SerializeMap>
+    {
+        Ok(SerializeMap { map: Map::new(), key: None })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<SerializeMap>
+    {
+        self.serialize_map(None)
+    }
+
+    /// Written as `Variant { .. }`, whose name is dropped like the names
+    /// of structs.
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<SerializeMap>
+    {
+        self.serialize_map(None)
+    }
+}
+
+/// A map from `variant` to `value`, as `Variant(..)` is read.
+fn tagged(variant: &str, value: Value) -> Value
+{
+    let mut map = Map::new();
+    map.insert(Value::String(variant.to_owned()), value);
+
+    Value::Map(map)
+}
+
+pub struct SerializeSeq
+{
+    items: Vec<Value>,
+    /// Empty tuples are written as `()`, which is read as the unit value,
+    /// except inside a tuple variant.
+    tuple: bool,
+    variant: Option<&'static str>,
+}
+
+impl SerializeSeq
+{
+    fn push<T>(&mut self, value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        self.items.push(value.serialize(Serializer)?);
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value>
+    {
+        let value = if self.tuple && self.items.is_empty() {
+            Value::Unit
+        } else {
+            Value::Seq(self.items)
+        };
+
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, value),
+            None => value,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeSeq
+{
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value>
+    {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeSeq
+{
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value>
+    {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeSeq
+{
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value>
+    {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeSeq
+{
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value>
+    {
+        self.finish()
+    }
+}
+
+pub struct SerializeMap
+{
+    map: Map,
+    key: Option<Value>,
+}
+
+impl SerializeMap
+{
+    fn field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        self.map.insert(Value::String(key.to_owned()), value.serialize(Serializer)?);
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for SerializeMap
+{
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        self.key = Some(key.serialize(Serializer)?);
+
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        let key = self.key.take().expect("Bug: value without key");
+        self.map.insert(key, value.serialize(Serializer)?);
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value>
+    {
+        Ok(Value::Map(self.map))
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap
+{
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value>
+    {
+        Ok(Value::Map(self.map))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap
+{
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+        where T: ?Sized + Serialize
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value>
+    {
+        Ok(Value::Map(self.map))
+    }
+}
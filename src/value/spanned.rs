@@ -0,0 +1,429 @@
+use std::mem;
+use std::str::FromStr;
+
+use cst::{self, Child, Node, NodeKind, Token, TokenKind};
+use de::{self, ParseError, Position};
+use parse::{Bytes, ParsedStr};
+use value::{Number, Value};
+
+/// A value together with its place in the input and the comments
+/// above it, for tools which need to point back at the source.
+///
+/// The structure follows `Value`: struct names are dropped, fields
+/// become string keys and `Variant(..)` becomes a map from the variant
+/// name to its content. Maps keep the order of the input. References to
+/// `let` bindings get the span of the reference and a copy of the
+/// content of the binding.
+///
+/// ```
+/// use rson_rs::de::Position;
+/// use rson_rs::value::{SpannedKind, SpannedValue};
+///
+/// let value: SpannedValue = "{\n    // The ground\n    texture: \"grass.png\",\n}".parse().unwrap();
+///
+/// if let SpannedKind::Map(ref entries) = value.kind {
+///     let (ref key, ref texture) = entries[0];
+///
+///     assert_eq!(key.start, Position { line: 3, col: 5 });
+///     assert_eq!(texture.start, Position { line: 3, col: 14 });
+///     assert_eq!(texture.comments, ["// The ground"]);
+/// }
+///
+/// assert_eq!(value.node_at(Position { line: 3, col: 20 }).unwrap().start.col, 14);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpannedValue
+{
+    /// The position of the first byte of the value.
+    pub start: Position,
+    /// The position right after the last byte of the value.
+    pub end: Position,
+    /// Comments on their own lines right above the value, or above
+    /// the key of a map entry.
+    pub comments: Vec<String>,
+    pub kind: SpannedKind,
+}
+
+/// The content of a `SpannedValue`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpannedKind
+{
+    Bool(bool),
+    Char(char),
+    Map(Vec<(SpannedValue, SpannedValue)>),
+    Number(Number),
+    Option(Option<Box<SpannedValue>>),
+    String(String),
+    Seq(Vec<SpannedValue>),
+    Unit,
+}
+
+impl FromStr for SpannedValue
+{
+    type Err = de::Error;
+
+    /// Parses `s`, keeping the positions and comments of all values.
+    fn from_str(s: &str) -> de::Result<Self>
+    {
+        let document = cst::parse(s)?;
+        let mut builder = Builder {
+            pos: Position { line: 1, col: 1 },
+            comments: Vec::new(),
+            new_line: true,
+            bindings: Vec::new(),
+        };
+
+        cst::check_expansion(&document)?;
+
+        let mut value = None;
+
+        for child in document.children() {
+            match *child {
+                Child::Token(ref t) => builder.token(t),
+                Child::Node(ref n) if n.kind() == NodeKind::Binding => builder.binding(n)?,
+                Child::Node(ref n) => value = Some(builder.value(n)?),
+            }
+        }
+
+        Ok(value.expect("Bug: document without value"))
+    }
+}
+
+impl SpannedValue
+{
+    /// Returns the innermost value whose span contains `pos`.
+    pub fn node_at(&self, pos: Position) -> Option<&SpannedValue>
+    {
+        let key = |p: Position| (p.line, p.col);
+
+        if key(pos) < key(self.start) || key(pos) >= key(self.end) {
+            return None;
+        }
+
+        let inner = match self.kind {
+            SpannedKind::Map(ref entries) => entries
+                .iter()
+                .filter_map(|(k, v)| k.node_at(pos).or_else(|| v.node_at(pos)))
+                .next(),
+            SpannedKind::Option(Some(ref v)) => v.node_at(pos),
+            SpannedKind::Seq(ref items) => items.iter().filter_map(|v| v.node_at(pos)).next(),
+            _ => None,
+        };
+
+        inner.or(Some(self))
+    }
+
+    /// Drops the positions and comments.
+    pub fn to_value(&self) -> Value
+    {
+        match self.kind {
+            SpannedKind::Bool(b) => Value::Bool(b),
+            SpannedKind::Char(c) => Value::Char(c),
+            SpannedKind::Map(ref entries) => {
+                Value::Map(entries.iter().map(|(k, v)| (k.to_value(), v.to_value())).collect())
+            }
+            SpannedKind::Number(n) => Value::Number(n),
+            SpannedKind::Option(ref o) => Value::Option(o.as_ref().map(|v| Box::new(v.to_value()))),
+            SpannedKind::String(ref s) => Value::String(s.clone()),
+            SpannedKind::Seq(ref items) => Value::Seq(items.iter().map(SpannedValue::to_value).collect()),
+            SpannedKind::Unit => Value::Unit,
+        }
+    }
+}
+
+/// Walks the syntax tree, keeping track of the position and of
+/// comments which belong to the next value.
+struct Builder
+{
+    pos: Position,
+    comments: Vec<String>,
+    /// True if there was a newline since the last significant token,
+    /// so a comment isn't a trailing comment of the value before.
+    new_line: bool,
+    /// The `let` bindings so far.
+    bindings: Vec<(String, SpannedValue)>,
+}
+
+impl Builder
+{
+    fn token(&mut self, token: &Token)
+    {
+        match token.kind() {
+            TokenKind::Whitespace => self.new_line |= token.text().contains('\n'),
+            TokenKind::LineComment | TokenKind::BlockComment => {
+                if self.new_line {
+                    self.comments.push(token.text().to_owned());
+                }
+            }
+            _ => {
+                self.comments.clear();
+                self.new_line = false;
+            }
+        }
+
+        self.pos.advance(token.text().as_bytes());
+    }
+
+    fn spanned(&self, start: Position, comments: Vec<String>, kind: SpannedKind) -> SpannedValue
+    {
+        SpannedValue { start, end: self.pos, comments, kind }
+    }
+
+    fn value(&mut self, node: &Node) -> de::Result<SpannedValue>
+    {
+        let start = self.pos;
+        let comments = mem::take(&mut self.comments);
+
+        let kind = match node.kind() {
+            NodeKind::Literal => {
+                let token = node.token().expect("Bug: empty literal");
+                let reference = self
+                    .bindings
+                    .iter()
+                    .rev()
+                    .find(|b| token.kind() == TokenKind::Ident && b.0 == token.text())
+                    .map(|b| b.1.kind.clone());
+
+                match reference {
+                    Some(kind) => {
+                        self.token(token);
+
+                        kind
+                    }
+                    None => self.literal(node)?,
+                }
+            }
+            NodeKind::Seq => SpannedKind::Seq(self.items(node)?),
+            NodeKind::Tuple => {
+                let name = node.name().map(str::to_owned);
+                let mut items = self.items(node)?;
+
+                match name {
+                    Some(ref n) if n == "Some" && items.len() == 1 => {
+                        SpannedKind::Option(Some(Box::new(items.remove(0))))
+                    }
+                    Some(n) => {
+                        let name_end = Position { line: start.line, col: start.col + n.len() };
+                        let content = if items.len() == 1 {
+                            items.remove(0)
+                        } else {
+                            self.spanned(name_end, Vec::new(), SpannedKind::Seq(items))
+                        };
+                        let key = SpannedValue {
+                            start,
+                            end: name_end,
+                            comments: Vec::new(),
+                            kind: SpannedKind::String(n),
+                        };
+
+                        SpannedKind::Map(vec![(key, content)])
+                    }
+                    None if items.is_empty() => SpannedKind::Unit,
+                    None => SpannedKind::Seq(items),
+                }
+            }
+            NodeKind::Struct | NodeKind::Map => {
+                let mut entries = Vec::new();
+
+                for child in node.children() {
+                    match *child {
+                        Child::Token(ref t) => self.token(t),
+                        Child::Node(ref entry) => entries.push(self.entry(entry)?),
+                    }
+                }
+
+                SpannedKind::Map(entries)
+            }
+            NodeKind::Include => return Err(de::Error::Parser(ParseError::UnexpandedInclude, start)),
+            NodeKind::Document | NodeKind::Entry | NodeKind::Binding => unreachable!("Bug: not a value"),
+        };
+
+        Ok(self.spanned(start, comments, kind))
+    }
+
+    /// Reads a literal without looking up bindings.
+    fn literal(&mut self, node: &Node) -> de::Result<SpannedKind>
+    {
+        let token = node.token().expect("Bug: empty literal");
+        let kind = literal(token).map_err(|e| de::Error::Parser(e, self.pos))?;
+
+        self.token(token);
+
+        Ok(kind)
+    }
+
+    fn binding(&mut self, binding: &Node) -> de::Result<()>
+    {
+        let mut value = None;
+
+        for child in binding.children() {
+            match *child {
+                Child::Token(ref t) => self.token(t),
+                Child::Node(ref n) => value = Some(self.value(n)?),
+            }
+        }
+
+        let name = binding.name().expect("Bug: binding without name").to_owned();
+        self.bindings.push((name, value.expect("Bug: binding without value")));
+
+        Ok(())
+    }
+
+    fn items(&mut self, node: &Node) -> de::Result<Vec<SpannedValue>>
+    {
+        let mut items = Vec::new();
+
+        for child in node.children() {
+            match *child {
+                Child::Token(ref t) => self.token(t),
+                Child::Node(ref n) => items.push(self.value(n)?),
+            }
+        }
+
+        Ok(items)
+    }
+
+    fn entry(&mut self, entry: &Node) -> de::Result<(SpannedValue, SpannedValue)>
+    {
+        let comments = mem::take(&mut self.comments);
+        let mut key = None;
+        let mut value = None;
+
+        for child in entry.children() {
+            match *child {
+                // A struct field.
+                Child::Token(ref t) if key.is_none() => {
+                    let start = self.pos;
+                    self.token(t);
+
+                    key = Some(self.spanned(start, Vec::new(), SpannedKind::String(t.text().to_owned())));
+                }
+                Child::Token(ref t) => self.token(t),
+                // Map keys aren't references.
+                Child::Node(ref n) if key.is_none() && n.kind() == NodeKind::Literal => {
+                    let start = self.pos;
+                    let kind = self.literal(n)?;
+
+                    key = Some(self.spanned(start, Vec::new(), kind));
+                }
+                Child::Node(ref n) if key.is_none() => key = Some(self.value(n)?),
+                Child::Node(ref n) => value = Some(self.value(n)?),
+            }
+        }
+
+        let mut value = value.expect("Bug: entry without value");
+        value.comments.splice(0..0, comments);
+
+        Ok((key.expect("Bug: entry without key"), value))
+    }
+}
+
+fn literal(token: &Token) -> Result<SpannedKind, ParseError>
+{
+    let text = token.text();
+    let mut bytes = Bytes::new(text.as_bytes());
+    let parse_error = |e: de::Error| match e {
+        de::Error::Parser(kind, _) => kind,
+        _ => ParseError::ExpectedFloat,
+    };
+
+    let value = match token.kind() {
+        TokenKind::Ident => match text {
+            "true" => SpannedKind::Bool(true),
+            "false" => SpannedKind::Bool(false),
+            "None" => SpannedKind::Option(None),
+            _ => SpannedKind::String(text.to_owned()),
+        },
+        TokenKind::Number => {
+            let n: f64 = bytes.float().map_err(parse_error)?;
+
+            if !n.is_finite() {
+                return Err(ParseError::ExpectedFloat);
+            }
+
+            SpannedKind::Number(Number::new(n))
+        }
+        TokenKind::String => SpannedKind::String(match bytes.string().map_err(parse_error)? {
+            ParsedStr::Allocated(s) => s,
+            ParsedStr::Slice(s) => s.to_owned(),
+        }),
+        TokenKind::Char => SpannedKind::Char(bytes.char().map_err(parse_error)?),
+        _ => unreachable!("Bug: not a literal"),
+    };
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    const INPUT: &str = "// A level
+Level {
+    size: (10, 20), // trailing
+    /* The player */
+    player: Some(Player { name: \"a\", items: [Sword, Shield(2), 'c', (), Empty()] }),
+    tags: { 1: None },
+}";
+
+    #[test]
+    fn test_same_as_value()
+    {
+        assert_eq!(INPUT.parse::<SpannedValue>().unwrap().to_value(), Value::from_str(INPUT).unwrap());
+    }
+
+    #[test]
+    fn test_spans()
+    {
+        let value: SpannedValue = INPUT.parse().unwrap();
+
+        assert_eq!((value.start, value.end), (Position { line: 2, col: 1 }, Position { line: 7, col: 2 }));
+        assert_eq!(value.comments, ["// A level"]);
+
+        let player = value.node_at(Position { line: 5, col: 13 }).unwrap();
+        let shield = value.node_at(Position { line: 5, col: 54 }).unwrap();
+
+        assert_eq!(player.comments, ["/* The player */"]);
+        assert_eq!(player.start, Position { line: 5, col: 13 });
+        assert_eq!(shield.kind, SpannedKind::String("Shield".to_owned()));
+        assert_eq!((shield.start, shield.end), (Position { line: 5, col: 53 }, Position { line: 5, col: 59 }));
+        assert!(value.node_at(Position { line: 8, col: 1 }).is_none());
+
+        match value.kind {
+            SpannedKind::Map(ref entries) => assert!(entries[2].1.comments.is_empty()),
+            _ => panic!("Expected a map"),
+        }
+    }
+
+    #[test]
+    fn test_bindings()
+    {
+        let value: SpannedValue = "let a = [1, 2];\n{ b: a, a: 3 }".parse().unwrap();
+
+        assert_eq!(value.to_value(), "{ b: [1, 2], a: 3 }".parse::<Value>().unwrap());
+
+        match value.kind {
+            SpannedKind::Map(ref entries) => {
+                let b = &entries[0].1;
+
+                assert_eq!((b.start, b.end), (Position { line: 2, col: 6 }, Position { line: 2, col: 7 }));
+                assert_eq!(entries[1].0.kind, SpannedKind::String("a".to_owned()));
+            }
+            _ => panic!("Expected a map"),
+        }
+    }
+
+    #[test]
+    fn test_error()
+    {
+        assert_eq!(
+            "[1,\n 1e999]".parse::<SpannedValue>(),
+            Err(de::Error::Parser(ParseError::ExpectedFloat, Position { line: 2, col: 2 })),
+        );
+        assert_eq!(
+            "{ a: include!(\"a\") }".parse::<SpannedValue>(),
+            Err(de::Error::Parser(ParseError::UnexpandedInclude, Position { line: 1, col: 6 })),
+        );
+    }
+}